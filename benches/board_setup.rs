@@ -0,0 +1,38 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use serde_json::json;
+
+use game_serve_rs::games::core::traits::Game;
+use game_serve_rs::games::hexagon::{Config, HexagonIsland};
+
+// Seating the last player is what lays out the board, so time just that
+fn board_setup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("board_setup");
+    for width in [3u8, 5, 7, 9, 11, 13, 15] {
+        let config: Config = serde_json::from_value(json!({
+            "num_players": 2,
+            "score_to_win": 10,
+            "game_board_width": width,
+            "seed": 42
+        })).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(width), &config, |b, config| {
+            b.iter_batched(
+                || {
+                    let mut game = HexagonIsland::new();
+                    game.configure_game(config.clone()).unwrap()
+                        .add_player("key1", "name1").unwrap();
+                    game
+                },
+                |mut game| {
+                    game.add_player("key2", "name2").unwrap();
+                    game
+                },
+                BatchSize::SmallInput
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, board_setup);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+use std::fmt;
+use serde::Serialize;
+
+/// Stable, machine-readable reason for turning something down
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    WrongPhase,
+    NotYourTurn,
+    ActionNotAllowed,
+    InvalidTarget,
+    InvalidIndex,
+    Occupied,
+    NotConnected,
+    TooClose,
+    NotEnoughResources,
+    InvalidResource,
+    MissingTrade,
+    InvalidTrade,
+    GameFull,
+    PlayerNotFound,
+    ReplayMismatch,
+    MalformedMessage,
+    UnsupportedVersion,
+    UnexpectedMessage,
+    Unauthorized,
+    EmptyDeck,
+    CardNotHeld,
+    InvalidConfig
+}
+
+/// Whatever the error was about, so clients can point at it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorContext {
+    Road(usize),
+    Node(usize),
+    Hex(usize),
+    Offer(usize),
+    Resource(String)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GameError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<ErrorContext>
+}
+
+impl GameError {
+    pub fn new(code: ErrorCode, message: &str) -> GameError {
+        GameError {
+            code,
+            message: String::from(message),
+            context: None
+        }
+    }
+
+    pub fn with_context(mut self, context: ErrorContext) -> GameError {
+        self.context = Some(context);
+
+        self
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
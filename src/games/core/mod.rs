@@ -5,6 +5,9 @@ use serde::{Serialize, Deserialize};
 pub mod traits;
 use traits::Game;
 
+pub mod error;
+use error::{ GameError, ErrorCode };
+
 pub mod playe;
 use playe::Players;
 
@@ -62,7 +65,7 @@ pub struct Core {
 }
 
 impl Game for Core {
-    type Status = Core;
+    type Status = String;
     type Command = CoreCommand;
     type Config = HashMap<String, CoreConfigType>;
 
@@ -100,34 +103,34 @@ impl Game for Core {
         self
     }
 
-    fn add_player(&mut self, key: &str, name: &str) -> Result<&mut Core, &'static str> {
+    fn add_player(&mut self, key: &str, name: &str) -> Result<&mut Core, GameError> {
         self.players.add_player(key, name);
 
         Ok(self)
     }
 
-    fn set_active_player(&mut self, key: &str) -> Result<&mut Core, &'static str> {
+    fn set_active_player(&mut self, key: &str) -> Result<&mut Core, GameError> {
         match self.players.set_active_player(key) {
             Ok(_) => Ok(self),
             Err(e) => Err(e)
         }
     }
     
-    fn next_player(&mut self) -> Result<&mut Core, &'static str> {
+    fn next_player(&mut self) -> Result<&mut Core, GameError> {
         match self.players.next_player(1) {
             Ok(_) => Ok(self),
             Err(e) => Err(e)
         }
     }
 
-    fn previous_player(&mut self) -> Result<&mut Core, &'static str> {
+    fn previous_player(&mut self) -> Result<&mut Core, GameError> {
         match self.players.next_player(-1) {
             Ok(_) => Ok(self),
             Err(e) => Err(e)
         }
     }
 
-    fn get_game_status(&self, key: &str) -> String {
+    fn get_game_status(&self, _key: &str) -> String {
         String::from("")
     }
 
@@ -135,26 +138,27 @@ impl Game for Core {
         self
     }
 
-    fn process_action(&mut self, command: Self::Command) -> Result<&mut Core, &'static str> {
+    fn process_action(&mut self, command: Self::Command) -> Result<&mut Core, GameError> {
         match self.phase {
             Phase::Setup | Phase::Play => match command.action {
                 Actions::None => Ok(self)
             },
-            _ => Err("Can only take action during the Setup or Play phases!")
+            _ => Err(GameError::new(ErrorCode::WrongPhase, "Can only take action during the Setup or Play phases!"))
         }
     }
 
-    fn configure_game(&mut self, config: Self::Config) -> Result<&mut Self, &'static str> {
+    fn configure_game(&mut self, config: Self::Config) -> Result<&mut Self, GameError> {
         match self.phase {
             Phase::Boot => {
                 self.config = config;
                 Ok(self)
             },
-            _ => Err("Cannot configure game outside of boot phase!")
+            _ => Err(GameError::new(ErrorCode::WrongPhase, "Cannot configure game outside of boot phase!"))
         }
     }
 }
 
 
 #[cfg(test)]
+#[allow(unused_variables, clippy::useless_vec)]
 mod test;
\ No newline at end of file
@@ -1,13 +1,15 @@
 use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 
+use super::error::{ GameError, ErrorCode };
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     pub key: String,
     pub name: String
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Players {
     pub list: Vec<Arc<Player>>,
     pub active_player: Option<Arc<Player>>,
@@ -40,21 +42,21 @@ impl Players {
         self
     }
 
-    pub fn set_active_player(&mut self, key: &str) -> Result<&mut Players, &'static str> {
+    pub fn set_active_player(&mut self, key: &str) -> Result<&mut Players, GameError> {
         let pki = self.list.iter().position(|p| p.key.as_str() == key);
         match pki {
             Some(pki) => {
                 self.active_player = Some(Arc::clone(&self.list[pki]));
                 Ok(self)
             },
-            None => Err("Player key not found!")
+            None => Err(GameError::new(ErrorCode::PlayerNotFound, "Player key not found!"))
         }  
     }
     
-    pub fn next_player(&mut self, advance: i8) -> Result<&mut Players, &'static str> {
+    pub fn next_player(&mut self, advance: i8) -> Result<&mut Players, GameError> {
         let active_player = match self.active_player.as_ref() {
             Some(ap) => ap,
-            None => return Err("There is no active player.")
+            None => return Err(GameError::new(ErrorCode::PlayerNotFound, "There is no active player."))
         };
         let active_player_index = self.list.iter().position(|p| p == active_player);
         match active_player_index {
@@ -63,7 +65,7 @@ impl Players {
                 self.active_player = Some(Arc::clone(&self.list[next_player_index]));
                 Ok(self)
             },
-            None => Err("Cannot get index of active player!")
+            None => Err(GameError::new(ErrorCode::PlayerNotFound, "Cannot get index of active player!"))
         }
     }
 
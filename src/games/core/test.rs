@@ -63,7 +63,7 @@ fn active_player() {
     assert_eq!(game.players.active_player.as_ref().unwrap().key, String::from("key2"));
     let attempt = game.set_active_player("not_a_valid_key"); // NOTE: No unwrap() to avoid a panic
     // println!("{:?}", attempt);
-    assert_eq!(attempt, Err("Player key not found!"));
+    assert_eq!(attempt, Err(GameError::new(ErrorCode::PlayerNotFound, "Player key not found!")));
     game.reset();
     assert_eq!(game.players.active_player, None);
 }
@@ -129,7 +129,7 @@ fn process_actions() {
         action: Actions::None
     };
     let attempt = game.process_action(command);
-    assert_eq!(attempt, Err("Can only take action during the Setup or Play phases!"));
+    assert_eq!(attempt, Err(GameError::new(ErrorCode::WrongPhase, "Can only take action during the Setup or Play phases!")));
     game.next_phase().next_phase();
     let command = CoreCommand {
         action: Actions::None
@@ -184,6 +184,22 @@ fn game_configuration() {
     let attempt = game.configure_game(config);
     assert_eq!(
         attempt,
-        Err("Cannot configure game outside of boot phase!")
+        Err(GameError::new(ErrorCode::WrongPhase, "Cannot configure game outside of boot phase!"))
     );
-}
\ No newline at end of file
+}
+#[test]
+fn serialize_errors() {
+    let error = GameError::new(ErrorCode::InvalidIndex, "Cannot build road; invalid road index.")
+        .with_context(error::ErrorContext::Road(3));
+    assert_eq!(
+        serde_json::to_string(&error).unwrap(),
+        "{\"code\":\"invalid_index\",\"message\":\"Cannot build road; invalid road index.\",\"context\":{\"road\":3}}"
+    );
+
+    let error = GameError::new(ErrorCode::NotYourTurn, "It is not your turn.");
+    assert_eq!(
+        serde_json::to_string(&error).unwrap(),
+        "{\"code\":\"not_your_turn\",\"message\":\"It is not your turn.\"}"
+    );
+    assert_eq!(error.to_string(), "It is not your turn.");
+}
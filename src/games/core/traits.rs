@@ -1,4 +1,6 @@
 
+use super::error::GameError;
+
 pub trait Game {
     type Status;
     type Command;
@@ -8,12 +10,12 @@ pub trait Game {
     fn next_phase(&mut self) -> &mut Self;
     fn next_round(&mut self) -> &mut Self;
     fn reset(&mut self) -> &mut Self;
-    fn add_player(&mut self, key: &str, name: &str) -> Result<&mut Self, &'static str>;
-    fn set_active_player(&mut self, key: &str) -> Result<&mut Self, &'static str>;
-    fn next_player(&mut self) -> Result<&mut Self, &'static str>;
-    fn previous_player(&mut self) -> Result<&mut Self, &'static str>;
-    fn get_game_status(&self, key: &str) -> String;
+    fn add_player(&mut self, key: &str, name: &str) -> Result<&mut Self, GameError>;
+    fn set_active_player(&mut self, key: &str) -> Result<&mut Self, GameError>;
+    fn next_player(&mut self) -> Result<&mut Self, GameError>;
+    fn previous_player(&mut self) -> Result<&mut Self, GameError>;
+    fn get_game_status(&self, key: &str) -> Self::Status;
     fn find_the_winner(&mut self) -> &mut Self;
-    fn process_action(&mut self, command: Self::Command) -> Result<&mut Self, &'static str>;
-    fn configure_game(&mut self, config: Self::Config) -> Result<&mut Self, &'static str>;
+    fn process_action(&mut self, command: Self::Command) -> Result<&mut Self, GameError>;
+    fn configure_game(&mut self, config: Self::Config) -> Result<&mut Self, GameError>;
 }
\ No newline at end of file
@@ -1,8 +1,11 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use serde::{Serialize, Deserialize};
 
-use super::board::{ Road, Node, BuildingType };
+use super::board::{ Road, Node, BuildingType, Adjacency };
 use super::resources::{Resource};
+use super::trading::Bundle;
+use super::cards::Play;
+use crate::games::core::error::{ GameError, ErrorCode, ErrorContext };
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Actions {
@@ -11,7 +14,12 @@ pub enum Actions {
     MoveScorpion,
     BuildStuff,
     Trade,
+    OfferTrade,
+    AcceptTrade,
+    RejectTrade,
+    CounterTrade,
     BuyBug,
+    PlayCard,
     EndTurn,
     None
 }
@@ -20,15 +28,23 @@ pub enum Actions {
 pub enum Target {
     Road,
     Node,
-    Hex
+    Hex,
+    Offer,
+    Town,
+    Player
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Command {
     pub action: Actions,
+    #[serde(default)]
     pub player: String,
     pub target: [Option<(Target,usize)>; 5],
-    pub trade: Option<(Resource,Resource)>
+    pub trade: Option<(Resource,Resource)>,
+    #[serde(default)]
+    pub offer: Option<Bundle>,
+    #[serde(default)]
+    pub play: Option<Play>
 }
 
 impl Command {
@@ -37,7 +53,9 @@ impl Command {
             action, 
             player,
             target: [None; 5],
-            trade: None
+            trade: None,
+            offer: None,
+            play: None
         }
     }
 
@@ -63,8 +81,16 @@ impl Command {
             .collect()
     }
 
-    pub fn get_trade(&self) -> Result<(Resource,Resource),&'static str> {
-        self.trade.ok_or("No resources were specified in the trade.")
+    pub fn get_trade(&self) -> Result<(Resource,Resource),GameError> {
+        self.trade.ok_or_else(|| GameError::new(ErrorCode::MissingTrade, "No resources were specified in the trade."))
+    }
+
+    pub fn get_play(&self) -> Result<Play,GameError> {
+        self.play.ok_or_else(|| GameError::new(ErrorCode::CardNotHeld, "No card was chosen to play."))
+    }
+
+    pub fn get_offer(&self) -> Result<Bundle,GameError> {
+        self.offer.ok_or_else(|| GameError::new(ErrorCode::MissingTrade, "No resources were specified in the offer."))
     }
 }
 
@@ -80,33 +106,44 @@ pub fn next_allowed_actions(last_action: &Actions, roll_sum: u8) -> Vec<Actions>
                 ],
                 _ =>vec![
                     Actions::Trade,
+                    Actions::OfferTrade,
                     Actions::BuildStuff,
                     Actions::BuyBug,
+                    Actions::PlayCard,
                     Actions::EndTurn
                 ]
             }
         },
         Actions::MoveScorpion => vec![
             Actions::Trade,
+            Actions::OfferTrade,
             Actions::BuildStuff,
             Actions::BuyBug,
+            Actions::PlayCard,
             Actions::EndTurn
         ],
         Actions::BuildStuff => vec![
             Actions::Trade,
+            Actions::OfferTrade,
             Actions::BuildStuff,
             Actions::BuyBug,
+            Actions::PlayCard,
             Actions::EndTurn
         ],
-        Actions::Trade => vec![
+        Actions::Trade |
+        Actions::OfferTrade |
+        Actions::AcceptTrade |
+        Actions::RejectTrade |
+        Actions::CounterTrade |
+        Actions::BuyBug |
+        Actions::PlayCard => vec![
             Actions::Trade,
+            Actions::OfferTrade,
             Actions::BuildStuff,
             Actions::BuyBug,
+            Actions::PlayCard,
             Actions::EndTurn
         ],
-        Actions::BuyBug => vec![
-            Actions::MoveScorpion
-        ],
         Actions::EndTurn => vec![
             Actions::RollDice
         ],
@@ -116,8 +153,7 @@ pub fn next_allowed_actions(last_action: &Actions, roll_sum: u8) -> Vec<Actions>
     }
 }
 
-pub fn roll_dice() -> (u8,u8) {
-    let mut rng = thread_rng();
+pub fn roll_dice<R: Rng>(rng: &mut R) -> (u8,u8) {
     (
         rng.gen_range(1..=6), 
         rng.gen_range(1..=6)
@@ -129,17 +165,24 @@ pub fn build_road(
     player_key: String, 
     nodes: &[Node], 
     roads: &mut Vec<Road>,
+    adjacency: &Adjacency,
     is_setup: bool
-) -> Result<(), &'static str> {
+) -> Result<(), GameError> {
 
     // Check for valid road index
     if road_index >= roads.len() {
-        return Err("Cannot build road; invalid road index.");
+        return Err(
+            GameError::new(ErrorCode::InvalidIndex, "Cannot build road; invalid road index.")
+                .with_context(ErrorContext::Road(road_index))
+        );
     }
 
     // Check if there is already a built road on this index
     if roads[road_index].player_key != None {
-        return Err("Cannot build road; there is already something there.");
+        return Err(
+            GameError::new(ErrorCode::Occupied, "Cannot build road; there is already something there.")
+                .with_context(ErrorContext::Road(road_index))
+        );
     }
 
     if is_setup == false {
@@ -152,21 +195,15 @@ pub fn build_road(
         }
 
         // Is there an adjacent road owned by this player?
-        let no_adjacent_road = !roads.iter().fold(
-            false,
-            | acc, cv | {
-                let ind_align = 
-                    cv.inds.0 == idx1 ||
-                    cv.inds.1 == idx1 ||
-                    cv.inds.0 == idx2 ||
-                    cv.inds.1 == idx2;
-                let player_align = cv.player_key == some_player_key_clone;
-                acc || (ind_align && player_align)
-            }
-        );
+        let no_adjacent_road = ![idx1, idx2].iter()
+            .flat_map(|&idx| &adjacency.node_roads[idx])
+            .any(|&road| roads[road].player_key == some_player_key_clone);
 
         if no_adjacent_building && no_adjacent_road {
-            return Err("Roads have to be built next to other roads or buildings you own.");
+            return Err(
+                GameError::new(ErrorCode::NotConnected, "Roads have to be built next to other roads or buildings you own.")
+                    .with_context(ErrorContext::Road(road_index))
+            );
         }
     }
 
@@ -181,55 +218,50 @@ pub fn build_node(
     player_key: String, 
     nodes: &mut Vec<Node>, 
     roads: &[Road],
+    adjacency: &Adjacency,
     is_setup: bool
-) -> Result<(), &'static str> {
+) -> Result<(), GameError> {
 
     // Check for valid node index
     if node_index >= nodes.len() {
-        return Err("Cannot make building; invalid node index.");
+        return Err(
+            GameError::new(ErrorCode::InvalidIndex, "Cannot make building; invalid node index.")
+                .with_context(ErrorContext::Node(node_index))
+        );
     }
 
     // Check if there is already a built node on this index
     if nodes[node_index].player_key != None {
-        return Err("Cannot make building; there is already something there.");
+        return Err(
+            GameError::new(ErrorCode::Occupied, "Cannot make building; there is already something there.")
+                .with_context(ErrorContext::Node(node_index))
+        );
     }
 
-    // Find adjacent nodes
-    let adjacent_nodes: Vec<usize> = roads.iter().filter(
-        | r | r.inds.0 == node_index || r.inds.1 == node_index
-    ).map(
-        | r | if r.inds.0 == node_index { r.inds.1 } else { r.inds.0 }
-    ).collect();
-
     // Check if there are buildings on adjacent nodes
     let mut is_adjacent_building = false;
-    for idx in adjacent_nodes {
+    for &idx in &adjacency.node_nodes[node_index] {
         if nodes[idx].player_key != None { is_adjacent_building = true; }
     }
     if is_adjacent_building {
-        return Err("Cannot make building; you must respect the two-space rule.");
+        return Err(
+            GameError::new(ErrorCode::TooClose, "Cannot make building; you must respect the two-space rule.")
+                .with_context(ErrorContext::Node(node_index))
+        );
     }
 
     // Is there an adjacent road owned by this player?
     // NOTE: Only check this outside of the setup phase
     if is_setup == false {
         let some_player_key_clone = Some(player_key.clone());
-        let no_adjacent_roads: bool = roads.iter().fold(
-            true,
-            | acc, cv | {
-                if (
-                    cv.inds.0 == node_index ||
-                    cv.inds.1 == node_index
-                ) && cv.player_key == some_player_key_clone {
-                    false
-                } else {
-                    acc
-                }
-            }
-        );
+        let no_adjacent_roads = !adjacency.node_roads[node_index].iter()
+            .any(|&road| roads[road].player_key == some_player_key_clone);
 
         if no_adjacent_roads {
-            return Err("Cannot make building; after initial setup you must build next to roads that you own.");
+            return Err(
+                GameError::new(ErrorCode::NotConnected, "Cannot make building; after initial setup you must build next to roads that you own.")
+                    .with_context(ErrorContext::Node(node_index))
+            );
         }
     }
 
@@ -240,6 +272,30 @@ pub fn build_node(
 
 }
 
+pub fn upgrade_node(node_index: usize, player_key: &str, nodes: &mut [Node]) -> Result<(), GameError> {
+
+    // Check for valid node index
+    if node_index >= nodes.len() {
+        return Err(
+            GameError::new(ErrorCode::InvalidIndex, "Cannot upgrade building; invalid node index.")
+                .with_context(ErrorContext::Node(node_index))
+        );
+    }
+
+    // Only your own villages can become towns
+    let node = &mut nodes[node_index];
+    if node.player_key.as_deref() != Some(player_key) || node.building_type != BuildingType::Village {
+        return Err(
+            GameError::new(ErrorCode::InvalidTarget, "Cannot upgrade building; you can only upgrade your own villages.")
+                .with_context(ErrorContext::Node(node_index))
+        );
+    }
+
+    node.building_type = BuildingType::Town;
+
+    Ok(())
+}
+
 // TODO: Create a trait around having a player key and then make this function generic
 pub fn count_player_nodes(player_key: &String, nodes: &[Node]) -> u8 {
     let num_player_nodes: u8 = nodes.iter().fold(
@@ -257,6 +313,18 @@ pub fn count_player_nodes(player_key: &String, nodes: &[Node]) -> u8 {
     num_player_nodes
 }
 
+/// Points from buildings: one per village and two per town
+pub fn score_player_nodes(player_key: &str, nodes: &[Node]) -> u8 {
+    nodes.iter().fold(
+        0,
+        | acc, cv | match (cv.player_key.as_deref(), &cv.building_type) {
+            (Some(pk), BuildingType::Town) if pk == player_key => acc + 2,
+            (Some(pk), _) if pk == player_key => acc + 1,
+            _ => acc
+        }
+    )
+}
+
 // TODO: Create a trait around having a player key and then make this function generic
 pub fn count_player_roads(player_key: &String, roads: &[Road]) -> usize {
     let num_player_roads: usize = roads.iter().fold(
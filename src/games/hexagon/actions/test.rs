@@ -1,5 +1,6 @@
 use super::*;
-use crate::games::hexagon::board::{ GameBoard };
+use rand::thread_rng;
+use crate::games::hexagon::board::{ GameBoard, BoardMap, BuildingType, NumberPlacement };
 
 #[test]
 fn allowed_actions() {
@@ -11,8 +12,10 @@ fn allowed_actions() {
         next_allowed_actions(&Actions::RollDice, 2),
         vec![
             Actions::Trade,
+            Actions::OfferTrade,
             Actions::BuildStuff,
             Actions::BuyBug,
+            Actions::PlayCard,
             Actions::EndTurn
         ]
     );
@@ -42,9 +45,7 @@ fn dice_distribution() {
     const NUM_TRIALS: usize = 10000;
 
     struct Bin {
-        bin: usize,
         count: usize,
-        expected_probability: f64,
         expected_value: f64,
         standard_deviation: f64
     }
@@ -56,9 +57,7 @@ fn dice_distribution() {
             let expected_value = NUM_TRIALS as f64 * expected_probability;
             let standard_deviation = f64::sqrt(expected_value * (1.0 - expected_probability));
             Bin {
-                bin: idx + 2,
                 count: 0,
-                expected_probability,
                 expected_value,
                 standard_deviation
             }
@@ -66,7 +65,7 @@ fn dice_distribution() {
     ).collect();
 
     for _trial in 0..NUM_TRIALS {
-        let roll_result = roll_dice();
+        let roll_result = roll_dice(&mut thread_rng());
         let roll_sum = roll_result.0 + roll_result.1;
         histogram[roll_sum as usize - 2].count += 1;
     }
@@ -87,7 +86,7 @@ fn dice_distribution() {
 #[test]
 fn build_a_road() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     let num_built_roads = board.roads.iter().fold(
         0, 
@@ -97,11 +96,11 @@ fn build_a_road() {
 
     let node_index = 0;
     let player_key = String::from("key1");
-    let _status = build_node(node_index, player_key, &mut board.nodes, &board.roads, true);
+    let _status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, true);
 
     let road_index = 0;
     let player_key = String::from("key1");
-    let _status = build_road(road_index, player_key, &board.nodes, &mut board.roads, false);
+    let _status = build_road(road_index, player_key, &board.nodes, &mut board.roads, &board.adjacency, false);
 
     let num_built_roads = board.roads.iter().fold(
         0, 
@@ -113,7 +112,7 @@ fn build_a_road() {
 #[test]
 fn build_on_a_node() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     let num_built_nodes = board.nodes.iter().fold(
         0, 
@@ -123,7 +122,7 @@ fn build_on_a_node() {
 
     let node_index = 0;
     let player_key = String::from("key1");
-    let _status = build_node(node_index, player_key, &mut board.nodes, &board.roads, true);
+    let _status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, true);
 
     let num_built_nodes = board.nodes.iter().fold(
         0, 
@@ -135,72 +134,120 @@ fn build_on_a_node() {
 #[test]
 fn node_building_errors() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     let node_index = 10000000;
     let player_key = String::from("key1");
     let is_setup = true;
-    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, is_setup);
-    assert_eq!(status, Err("Cannot make building; invalid node index."));
+    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, is_setup);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::InvalidIndex, "Cannot make building; invalid node index.").with_context(ErrorContext::Node(node_index)))
+    );
 
     let node_index = 0;
     let player_key = String::from("key1");
     let is_setup = true;
-    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, is_setup);
+    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, is_setup);
     assert_eq!(status, Ok(()));
 
     let node_index = 0;
     let player_key = String::from("key1");
     let is_setup = true;
-    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, is_setup);
-    assert_eq!(status, Err("Cannot make building; there is already something there."));
+    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, is_setup);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::Occupied, "Cannot make building; there is already something there.").with_context(ErrorContext::Node(node_index)))
+    );
 
     let node_index = 1;
     let player_key = String::from("key1");
     let is_setup = true;
-    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, is_setup);
-    assert_eq!(status, Err("Cannot make building; you must respect the two-space rule."));
+    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, is_setup);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::TooClose, "Cannot make building; you must respect the two-space rule.").with_context(ErrorContext::Node(node_index)))
+    );
 
     let node_index = 10;
     let player_key = String::from("key1");
     let is_setup = false;
-    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, is_setup);
-    assert_eq!(status, Err("Cannot make building; after initial setup you must build next to roads that you own."));
+    let status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, is_setup);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::NotConnected, "Cannot make building; after initial setup you must build next to roads that you own.").with_context(ErrorContext::Node(node_index)))
+    );
 
 }
 
 #[test]
 fn road_building_errors() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     let road_index = 10000000;
     let player_key = String::from("key1");
-    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, false);
-    assert_eq!(status, Err("Cannot build road; invalid road index."));
+    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, &board.adjacency, false);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::InvalidIndex, "Cannot build road; invalid road index.").with_context(ErrorContext::Road(road_index)))
+    );
 
     let node_index = 0;
     let player_key = String::from("key1");
     let is_setup = true;
-    let _status = build_node(node_index, player_key, &mut board.nodes, &board.roads, is_setup);
+    let _status = build_node(node_index, player_key, &mut board.nodes, &board.roads, &board.adjacency, is_setup);
 
     let road_index = 0;
     let player_key = String::from("key1");
-    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, false);
+    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, &board.adjacency, false);
     assert_eq!(status, Ok(()));
 
     let road_index = 0;
     let player_key = String::from("key1");
-    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, false);
-    assert_eq!(status, Err("Cannot build road; there is already something there."));
+    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, &board.adjacency, false);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::Occupied, "Cannot build road; there is already something there.").with_context(ErrorContext::Road(road_index)))
+    );
 
     let road_index = 10;
     let player_key = String::from("key1");
-    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, false);
-    assert_eq!(status, Err("Roads have to be built next to other roads or buildings you own."));
+    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, &board.adjacency, false);
+    assert_eq!(
+        status,
+        Err(GameError::new(ErrorCode::NotConnected, "Roads have to be built next to other roads or buildings you own.").with_context(ErrorContext::Road(road_index)))
+    );
 
     let road_index = 1;
     let player_key = String::from("key1");
-    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, false);
+    let status = build_road(road_index, player_key, &board.nodes, &mut board.roads, &board.adjacency, false);
     assert_eq!(status, Ok(()));
-}
\ No newline at end of file
+}
+#[test]
+fn upgrade_a_village() {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+
+    let status = upgrade_node(0, "key1", &mut board.nodes);
+    assert_eq!(status.unwrap_err().code, ErrorCode::InvalidTarget);
+
+    build_node(0, String::from("key1"), &mut board.nodes, &board.roads, &board.adjacency, true).unwrap();
+    build_node(10, String::from("key1"), &mut board.nodes, &board.roads, &board.adjacency, true).unwrap();
+    assert_eq!(score_player_nodes("key1", &board.nodes), 2);
+
+    let status = upgrade_node(0, "key2", &mut board.nodes);
+    assert_eq!(status.unwrap_err().code, ErrorCode::InvalidTarget);
+
+    upgrade_node(0, "key1", &mut board.nodes).unwrap();
+    assert_eq!(board.nodes[0].building_type, BuildingType::Town);
+    assert_eq!(count_player_nodes(&String::from("key1"), &board.nodes), 2);
+    assert_eq!(score_player_nodes("key1", &board.nodes), 3);
+
+    // A town can't be upgraded again
+    let status = upgrade_node(0, "key1", &mut board.nodes);
+    assert_eq!(status.unwrap_err().code, ErrorCode::InvalidTarget);
+
+    let status = upgrade_node(10000000, "key1", &mut board.nodes);
+    assert_eq!(status.unwrap_err().code, ErrorCode::InvalidIndex);
+}
@@ -0,0 +1,77 @@
+use serde::{Serialize, Deserialize};
+
+/// A hexagon in axial coordinates; the third cube coordinate is `-q-r`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Hex {
+    pub q: i16,
+    pub r: i16
+}
+
+/// A corner, named by the sum of the axial coordinates of the three hexagons that meet there
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Vertex {
+    pub q: i16,
+    pub r: i16
+}
+
+/// A side, named by the sum of the vertex coordinates of its two ends
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Edge {
+    pub q: i16,
+    pub r: i16
+}
+
+const DIRECTIONS: [(i16, i16); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+// Where each corner sits relative to three times the hexagon, going around from the bottom
+const CORNERS: [(i16, i16); 6] = [(-1, 2), (1, 1), (2, -1), (1, -2), (-1, -1), (-2, 1)];
+
+impl Hex {
+    pub fn new(q: i16, r: i16) -> Hex {
+        Hex { q, r }
+    }
+
+    pub fn s(&self) -> i16 {
+        -self.q - self.r
+    }
+
+    pub fn neighbors(&self) -> [Hex; 6] {
+        DIRECTIONS.map(|(dq, dr)| Hex::new(self.q + dq, self.r + dr))
+    }
+
+    /// How many steps it takes to walk from one hexagon to the other
+    pub fn distance(&self, other: Hex) -> i16 {
+        ((self.q - other.q).abs() + (self.r - other.r).abs() + (self.s() - other.s()).abs()) / 2
+    }
+
+    pub fn vertices(&self) -> [Vertex; 6] {
+        CORNERS.map(|(dq, dr)| Vertex { q: 3 * self.q + dq, r: 3 * self.r + dr })
+    }
+
+    /// The sides in the same order as the board has always listed its roads, each one ending at the matching corner
+    pub fn edges(&self) -> [(Vertex, Vertex); 6] {
+        let vertices = self.vertices();
+        [0, 1, 2, 3, 4, 5].map(|step| (vertices[(step + 5) % 6], vertices[step]))
+    }
+
+    /// Where the center is drawn, in units of the distance between centers
+    pub fn to_pixel(self) -> (f64, f64) {
+        (f64::from(self.q) + f64::from(self.r) / 2.0, f64::from(self.r) * f64::sqrt(3.0/4.0))
+    }
+}
+
+impl Vertex {
+    /// Where the corner is drawn, in the same units as `Hex::to_pixel`
+    pub fn to_pixel(self) -> (f64, f64) {
+        (
+            (f64::from(self.q) + f64::from(self.r) / 2.0) / 3.0,
+            f64::from(self.r) * f64::sqrt(3.0/4.0) / 3.0
+        )
+    }
+}
+
+impl Edge {
+    pub fn between(a: Vertex, b: Vertex) -> Edge {
+        Edge { q: a.q + b.q, r: a.r + b.r }
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+
+use crate::games::hexagon::resources::Resource;
+use crate::games::core::error::{ GameError, ErrorCode };
+use super::Hex;
+
+/// The names of the maps that ship with the server
+pub const BUILTIN_MAPS: [&str; 3] = ["classic", "rectangle", "archipelago"];
+
+// A hexagon fifteen wide, which is already more than anyone could finish
+const MAX_TILES: usize = 169;
+// How many steps from the center a tile can be, which keeps the corner coordinates well inside an i16
+const MAX_DISTANCE: i32 = 15;
+
+/// One hexagon of land at axial coordinates (q,r); anything left out is dealt at random
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapTile {
+    pub q: i16,
+    pub r: i16,
+    #[serde(default)]
+    pub resource: Option<Resource>,
+    #[serde(default)]
+    pub number: Option<u8>
+}
+
+/// Where the land is; the nodes and roads are worked out from this
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoardMap {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub tiles: Vec<MapTile>
+}
+
+impl MapTile {
+    pub fn hex(&self) -> Hex {
+        Hex::new(self.q, self.r)
+    }
+}
+
+impl BoardMap {
+    /// A hexagon shaped island `width` tiles across the middle, listed row by row from the top
+    pub fn hexagon(width: u8) -> BoardMap {
+        let n = (i16::from(width) - 1) / 2;
+        let mut tiles = Vec::new();
+        for r in -n..=n {
+            for q in (-n).max(-n - r)..=n.min(n - r) {
+                tiles.push(MapTile { q, r, resource: None, number: None });
+            }
+        }
+
+        BoardMap { name: String::from("hexagon"), description: format!("A hexagon {} wide", width), tiles }
+    }
+
+    pub fn builtin(name: &str) -> Option<BoardMap> {
+        let json = match name {
+            "classic" => include_str!("../../../../assets/maps/classic.json"),
+            "rectangle" => include_str!("../../../../assets/maps/rectangle.json"),
+            "archipelago" => include_str!("../../../../assets/maps/archipelago.json"),
+            _ => return None
+        };
+
+        Some(serde_json::from_str(json).expect("built-in maps are valid JSON"))
+    }
+
+    /// Rejects maps that can't be laid out
+    pub fn validate(&self) -> Result<(), GameError> {
+        let invalid = |message: String| Err(GameError::new(ErrorCode::InvalidConfig, &message));
+
+        if self.tiles.is_empty() || self.tiles.len() > MAX_TILES {
+            return invalid(format!("Maps need between 1 and {} tiles.", MAX_TILES));
+        }
+
+        let mut seen = HashSet::new();
+        for tile in &self.tiles {
+            let (q, r) = (i32::from(tile.q), i32::from(tile.r));
+            if q.abs().max(r.abs()).max((q + r).abs()) > MAX_DISTANCE {
+                return invalid(format!("The tile at ({},{}) is more than {} steps from the center.", tile.q, tile.r, MAX_DISTANCE));
+            }
+            if !seen.insert(tile.hex()) {
+                return invalid(format!("There is more than one tile at ({},{}).", tile.q, tile.r));
+            }
+            if let Some(number) = tile.number {
+                // A fixed number needs a fixed resource, or it could end up on the desert
+                let producing = tile.resource.is_some() && tile.resource != Some(Resource::Desert);
+                if !producing || !(2..=12).contains(&number) || number == 7 {
+                    return invalid(format!("The tile at ({},{}) can't have the number {}.", tile.q, tile.r, number));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
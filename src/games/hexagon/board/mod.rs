@@ -1,53 +1,151 @@
-use rand::thread_rng;
+use std::collections::{ HashMap, HashSet };
+use rand::Rng;
 use rand::seq::SliceRandom;
 use serde::{Serialize, Deserialize};
 
+use crate::games::core::error::GameError;
 use crate::games::hexagon::resources::Resource;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+mod coords;
+mod maps;
+mod numbers;
+pub use coords::{ Hex, Vertex, Edge };
+pub use maps::{ BoardMap, MapTile, BUILTIN_MAPS };
+pub use numbers::NumberPlacement;
+
+const CENTROID_SPACING: u8 = 100;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Coordinate {
     x: f64,
     y: f64
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Centroid {
     loc: Coordinate,
     number: u8
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Hexagon {
+    #[serde(default)]
+    pub hex: Hex,
     pub vertices: Vec<Coordinate>,
     pub number: u8,
     pub resource: Resource
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BuildingType {
     Village,
+    Town,
     Empty
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Node {
+    #[serde(default)]
+    pub vertex: Vertex,
     pub loc: Coordinate,
     pub player_key: Option<String>,
     pub building_type: BuildingType
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Road {
+    #[serde(default)]
+    pub edge: Edge,
     pub inds: (usize,usize),
     pub player_key: Option<String>
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Lets whoever builds at either end of `road` trade at `ratio`:1; generic when there's no resource
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Harbor {
+    pub road: usize,
+    pub resource: Option<Resource>,
+    pub ratio: u16
+}
+
+/// Who touches what, worked out once when the board is laid out or loaded
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Adjacency {
+    pub hex_nodes: Vec<[usize; 6]>,
+    pub node_hexes: Vec<Vec<usize>>,
+    pub node_roads: Vec<Vec<usize>>,
+    pub node_nodes: Vec<Vec<usize>>
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedBoard")]
 pub struct GameBoard {
     pub centroids: Vec<Centroid>,
     pub nodes: Vec<Node>,
     pub hexagons: Vec<Hexagon>,
     pub roads: Vec<Road>,
+    pub harbors: Vec<Harbor>,
+    #[serde(skip)]
+    pub adjacency: Adjacency,
+    pub scorpion_index: Option<usize>
+}
+
+// What a snapshot keeps of the board; the adjacency tables are worked out again on loading
+#[derive(Deserialize)]
+struct SavedBoard {
+    centroids: Vec<Centroid>,
+    nodes: Vec<Node>,
+    hexagons: Vec<Hexagon>,
+    roads: Vec<Road>,
+    #[serde(default)]
+    harbors: Vec<Harbor>,
+    scorpion_index: Option<usize>
+}
+
+impl TryFrom<SavedBoard> for GameBoard {
+    type Error = String;
+
+    fn try_from(saved: SavedBoard) -> Result<GameBoard, String> {
+        let mut board = GameBoard {
+            centroids: saved.centroids,
+            nodes: saved.nodes,
+            hexagons: saved.hexagons,
+            roads: saved.roads,
+            harbors: saved.harbors,
+            adjacency: Adjacency::default(),
+            scorpion_index: saved.scorpion_index
+        };
+        // Every node has its own vertex, so if they are all the same none were saved
+        if board.nodes.len() > 1 && board.nodes.iter().all(|node| node.vertex == board.nodes[0].vertex) {
+            board.recover_coordinates(CENTROID_SPACING)?;
+        }
+        board.build_adjacency();
+
+        Ok(board)
+    }
+}
+
+/// The parts of the board that never change once it is laid out
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BoardLayout {
+    pub centroids: Vec<Centroid>,
+    pub hexagons: Vec<Hexagon>,
+    pub nodes: Vec<Coordinate>,
+    pub roads: Vec<(usize,usize)>,
+    pub harbors: Vec<Harbor>
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NodeState {
+    pub owner: Option<usize>,
+    pub building_type: BuildingType
+}
+
+/// Who owns what and where the scorpion is; indices line up with the layout
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BoardState {
+    pub nodes: Vec<NodeState>,
+    pub roads: Vec<Option<usize>>,
     pub scorpion_index: Option<usize>
 }
 
@@ -58,6 +156,8 @@ impl GameBoard {
             nodes: Vec::new(), 
             hexagons: Vec::new(), 
             roads: Vec::new(),
+            harbors: Vec::new(),
+            adjacency: Adjacency::default(),
             scorpion_index: None
         }
     }
@@ -67,43 +167,79 @@ impl GameBoard {
         self.nodes.truncate(0);
         self.hexagons.truncate(0);
         self.roads.truncate(0);
+        self.harbors.truncate(0);
+        self.adjacency = Adjacency::default();
         self.scorpion_index = None;
 
         self
     }
 
-    pub fn setup(&mut self, game_board_width: u8) {
-        const CENTROID_SPACING: u8 = 100;
-        self.compute_hex_grid_centroids(CENTROID_SPACING, game_board_width);
-        self.assign_resources_and_rolls();
+    pub fn get_layout(&self) -> BoardLayout {
+        BoardLayout {
+            centroids: self.centroids.clone(),
+            hexagons: self.hexagons.clone(),
+            nodes: self.nodes.iter().map(|n| n.loc.clone()).collect(),
+            roads: self.roads.iter().map(|r| r.inds).collect(),
+            harbors: self.harbors.clone()
+        }
+    }
+
+    /// Ownership with each player key swapped for whatever `public_id` gives back
+    pub fn get_state<F: Fn(&str) -> Option<usize>>(&self, public_id: F) -> BoardState {
+        BoardState {
+            nodes: self.nodes.iter().map(
+                |n| NodeState {
+                    owner: n.player_key.as_deref().and_then(&public_id),
+                    building_type: n.building_type.clone()
+                }
+            ).collect(),
+            roads: self.roads.iter().map(|r| r.player_key.as_deref().and_then(&public_id)).collect(),
+            scorpion_index: self.scorpion_index
+        }
+    }
+
+    /// Lays out the board; the same random number generator state always produces the same board
+    pub fn setup<R: Rng>(&mut self, map: &BoardMap, placement: NumberPlacement, rng: &mut R) -> Result<(), GameError> {
+        self.compute_centroids(CENTROID_SPACING, &map.tiles);
+        self.assign_resources_and_rolls(&map.tiles, placement, rng)?;
         self.compute_nodes_and_roads(CENTROID_SPACING);
+        self.place_harbors(rng);
         self.scorpion_index = self.hexagons
             .iter()
             .position(|hex| { hex.resource == Resource::Desert });
 
+        Ok(())
     }
 
-    fn compute_hex_grid_centroids(&mut self, centroid_spacing: u8, game_board_width: u8) {
-        let num_off_center_rows: i8 = (game_board_width as i8 - 1) / 2;
-        for row in (-1 * num_off_center_rows)..=num_off_center_rows {
-            let num_hex_in_row = game_board_width as i8 - row.abs();
-            let vertical_offset: f64 = f64::from(row) * f64::sqrt(3.0/4.0);
-            let horizontal_offset: f64 = f64::from(row).abs() / 2.0;
-            for hex in 0..num_hex_in_row {
-                self.centroids.push( Centroid { 
-                    loc: Coordinate { 
-                        x: f64::from(centroid_spacing) * (horizontal_offset + f64::from(hex)), 
-                        y: f64::from(centroid_spacing) * (vertical_offset + f64::from(num_off_center_rows))
-                    }, 
-                    number: 0
-                });
-            }
+    fn compute_centroids(&mut self, centroid_spacing: u8, tiles: &[MapTile]) {
+        let locate = | tile: &MapTile | tile.hex().to_pixel();
+
+        // Start the board at the origin whatever coordinates the map uses
+        let (min_x, min_y) = tiles.iter().map(locate).fold(
+            (f64::INFINITY, f64::INFINITY),
+            | (min_x, min_y), (x, y) | (min_x.min(x), min_y.min(y))
+        );
+
+        for tile in tiles {
+            let (x, y) = locate(tile);
+            self.centroids.push( Centroid {
+                loc: Coordinate {
+                    x: f64::from(centroid_spacing) * (x - min_x),
+                    y: f64::from(centroid_spacing) * (y - min_y)
+                },
+                number: 0
+            });
         }
     }
 
-    fn assign_resources_and_rolls(&mut self) {
+    fn assign_resources_and_rolls<R: Rng>(&mut self, tiles: &[MapTile], placement: NumberPlacement, rng: &mut R) -> Result<(), GameError> {
 
-        let num_centroids = self.centroids.len() as f64;
+        // Only the tiles the map leaves open are dealt resources, along with a desert unless the map placed one
+        let open: Vec<usize> = (0..tiles.len()).filter(|&idx| tiles[idx].resource.is_none()).collect();
+        let has_desert = tiles.iter().any(|tile| tile.resource == Some(Resource::Desert));
+        let num_dealt = if has_desert || open.is_empty() { open.len() } else { open.len() - 1 };
+
+        let num_centroids = open.len() as f64;
         let canonical_count = 18.0;
 
         // Resource ratios
@@ -127,9 +263,23 @@ impl GameBoard {
         for _ in 0..num_timber { resources.push(Resource::Timber); }
         for _ in 0..num_cereal { resources.push(Resource::Cereal); }
         for _ in 0..num_fiber { resources.push(Resource::Fiber); }
-        resources.push(Resource::Desert);
-        let mut rng = thread_rng();
-        resources.shuffle(&mut rng);
+        // Rounding can come up short on small maps, so top up with the most common resources
+        let mut top_up = [Resource::Timber, Resource::Cereal, Resource::Fiber, Resource::Block, Resource::Rock].into_iter().cycle();
+        while resources.len() < num_dealt { resources.extend(top_up.next()); }
+        resources.truncate(num_dealt);
+        if num_dealt < open.len() { resources.push(Resource::Desert); }
+        resources.shuffle(rng);
+
+        let mut tile_resources: Vec<Resource> = tiles.iter().map(|tile| tile.resource.unwrap_or(Resource::Desert)).collect();
+        for (&idx, rsc) in open.iter().zip(resources.drain(..)) {
+            tile_resources[idx] = rsc;
+        }
+
+        // Numbers go on every tile the map didn't number, with a 1 for each desert among them
+        let unnumbered: Vec<usize> = (0..tiles.len()).filter(|&idx| tiles[idx].number.is_none()).collect();
+        let is_desert: Vec<bool> = unnumbered.iter().map(|&idx| tile_resources[idx] == Resource::Desert).collect();
+        let num_deserts = is_desert.iter().filter(|&&desert| desert).count();
+        let num_centroids = unnumbered.len() as f64;
 
         // Number ratios
         let two_ratio = 1.0 / canonical_count;
@@ -167,139 +317,230 @@ impl GameBoard {
         for _ in 0..num_ten { numbers.push(10); }
         for _ in 0..num_eleven { numbers.push(11); }
         for _ in 0..num_twelve { numbers.push(12); }
-        numbers.truncate(self.centroids.len()-1);
-        numbers.push(1); // for the Desert
-        numbers.shuffle(&mut rng);
-
-        // Make sure the desert and 1 are at the same index
-        let desert_index = resources.iter().position(|p| *p == Resource::Desert).unwrap();
-        let one_index = numbers.iter().position(|p| *p == 1).unwrap();
-        if desert_index != one_index {
-            numbers.swap(desert_index, one_index);
+        let mut top_up = [6, 8, 5, 9, 4, 10, 3, 11, 2, 12].into_iter().cycle();
+        while numbers.len() < unnumbered.len() - num_deserts { numbers.extend(top_up.next()); }
+        numbers.truncate(unnumbered.len() - num_deserts);
+        numbers.extend(vec![1; num_deserts]); // for the Desert
+        numbers.shuffle(rng);
+
+        // Make sure the deserts and 1s are at the same indices
+        for desert_index in (0..numbers.len()).filter(|&idx| is_desert[idx]) {
+            if numbers[desert_index] != 1 {
+                let one_index = (0..numbers.len()).find(|&idx| numbers[idx] == 1 && !is_desert[idx]).unwrap();
+                numbers.swap(desert_index, one_index);
+            }
+        }
+
+        let mut tile_numbers: Vec<u8> = tiles.iter().map(|tile| tile.number.unwrap_or(1)).collect();
+        for (&idx, num) in unnumbered.iter().zip(numbers) {
+            tile_numbers[idx] = num;
         }
 
-        for (idx, &num) in numbers.iter().enumerate() {
+        // Deserts and whatever the map numbered itself stay put
+        let movable: Vec<usize> = unnumbered.iter().copied().filter(|&idx| tile_resources[idx] != Resource::Desert).collect();
+        match placement {
+            NumberPlacement::Random => (),
+            NumberPlacement::Balanced => numbers::balance(tiles, &tile_resources, &mut tile_numbers, &movable, rng)?,
+            NumberPlacement::Spiral => {
+                let order = numbers::spiral_order(tiles, &movable, rng);
+                for (idx, num) in order.into_iter().zip(numbers::spiral_numbers(movable.len())) {
+                    tile_numbers[idx] = num;
+                }
+            }
+        }
+
+        for (idx, (num, rsc)) in tile_numbers.into_iter().zip(tile_resources).enumerate() {
             self.hexagons.push( Hexagon { 
+                hex: tiles[idx].hex(),
                 vertices: Vec::new(), 
                 number: num, 
-                resource: Resource::Desert
+                resource: rsc
             });
             self.centroids[idx].number = num;
         }
-        for (idx, rsc) in resources.drain(..).enumerate() {
-            self.hexagons[idx].resource = rsc;
-        }
+
+        Ok(())
     }
 
     fn compute_nodes_and_roads(&mut self, centroid_spacing: u8) {
-        // Distance from each centroid to their surrounding nodes (vertices)
-        let radius = f64::from(centroid_spacing) / f64::sqrt(3.0);
+        // Neighboring hexagons list the same corners and sides, so only the first of each is kept
+        let mut node_indices: HashMap<Vertex, usize> = HashMap::new();
+        let mut edges: HashSet<Edge> = HashSet::new();
 
-        // Loop over centroids and construct the nodes, roads, and hexagon vertices
         for (idx, el) in self.centroids.iter().enumerate() {
-            let node_idx = 6 * idx as usize;
-            // Find the [non-unique] six nodes around each hexagon centroid
-            for step in 0..6 {
-                let angle = step as f64 * std::f64::consts::PI / 3.0;
-                let x = f64::round( 1000.0 * ( radius * f64::sin(angle) + el.loc.x ) ) / 1000.0;
-                let y = f64::round( 1000.0 * ( radius * f64::cos(angle) + el.loc.y ) ) / 1000.0;
-                self.nodes.push( Node { loc: Coordinate { x, y }, player_key: None, building_type: BuildingType::Empty } );
-                self.hexagons[idx].vertices.push( Coordinate{x, y} );
-                if step == 0 { self.roads.push( Road { inds: (node_idx + 5, node_idx), player_key: None } ) }
-                else { self.roads.push( Road { inds: (node_idx+step-1, node_idx+step), player_key: None } ) }
+            let hex = self.hexagons[idx].hex;
+            let (hex_x, hex_y) = hex.to_pixel();
+
+            let corners = hex.vertices().map(
+                | vertex | *node_indices.entry(vertex).or_insert_with(|| {
+                    // Pixels are only for drawing; the vertex is what tells nodes apart
+                    let (x, y) = vertex.to_pixel();
+                    let x = f64::round( 1000.0 * ( f64::from(centroid_spacing) * (x - hex_x) + el.loc.x ) ) / 1000.0;
+                    let y = f64::round( 1000.0 * ( f64::from(centroid_spacing) * (y - hex_y) + el.loc.y ) ) / 1000.0;
+                    self.nodes.push( Node { vertex, loc: Coordinate { x, y }, player_key: None, building_type: BuildingType::Empty } );
+                    self.nodes.len() - 1
+                })
+            );
+
+            for (step, (from, to)) in hex.edges().into_iter().enumerate() {
+                let edge = Edge::between(from, to);
+                if edges.insert(edge) {
+                    self.roads.push( Road { edge, inds: (corners[(step + 5) % 6], corners[step]), player_key: None } );
+                }
             }
         }
 
-        // Now go through the list of nodes and reduce it down to the unique set
-        self.nodes = self.nodes.drain(..).enumerate().fold(
-            Vec::<Node>::new(),
-            | mut unique, (index,value) | {
-                let new_idx: Vec<usize> = unique.iter().enumerate().filter_map(
-                    | (idx,val) | {
-                        if val.loc.x == value.loc.x && val.loc.y == value.loc.y { Some(idx) }
-                        else { None }
-                    }
-                ).collect();
-                // Is `value` already in the `unique` vector?
-                if !new_idx.is_empty() {
-                    // Update the indices in `roads`
-                    for ni in new_idx {
-                        self.roads = self.roads.iter().map(
-                            | segment | {
-                                let s1 = if segment.inds.0 == index { ni } else { segment.inds.0 };
-                                let s2 = if segment.inds.1 == index { ni } else { segment.inds.1 };
-                                Road { inds: (s1,s2), player_key: None }
-                            }
-                        ).collect();
-                    }
-                    // And don't add the node to the `unique` list
-                    unique
-                } else { // If `value` is not already in `unique`, add it
-                    let inc_idx = unique.len();
-                    self.roads = self.roads.iter().map(
-                        | segment | {
-                            let s1 = if segment.inds.0 == index { inc_idx } else { segment.inds.0 };
-                            let s2 = if segment.inds.1 == index { inc_idx } else { segment.inds.1 };
-                            Road { inds: (s1,s2), player_key: None }
-                        }
-                    ).collect();
-                    // Add this node to the `unique` list
-                    unique.push(value);
-                    unique
-                }
+        self.build_adjacency();
+    }
+
+    // Older snapshots only kept where things are drawn, so the hex, vertex and edge keys are worked back out
+    fn recover_coordinates(&mut self, centroid_spacing: u8) -> Result<(), String> {
+        let spacing = f64::from(centroid_spacing);
+        let row_height = spacing * f64::sqrt(3.0/4.0);
+
+        // The board was shifted to start at the origin, which can leave every column off by the same half
+        let row = | centroid: &Centroid | f64::round(centroid.loc.y / row_height);
+        let column = | centroid: &Centroid | centroid.loc.x / spacing - row(centroid) / 2.0;
+        let shift = self.centroids.first().map_or(0.0, |centroid| column(centroid).rem_euclid(1.0));
+        for (hexagon, centroid) in self.hexagons.iter_mut().zip(&self.centroids) {
+            hexagon.hex = Hex::new(f64::round(column(centroid) - shift) as i16, row(centroid) as i16);
+        }
+
+        // Then every node should sit on a corner of one of those hexagons
+        let (origin_x, origin_y) = match (self.hexagons.first(), self.centroids.first()) {
+            (Some(hexagon), Some(centroid)) => {
+                let (x, y) = hexagon.hex.to_pixel();
+                (centroid.loc.x - spacing * x, centroid.loc.y - spacing * y)
+            },
+            _ => return Err(String::from("The board has nodes but no hexagons."))
+        };
+        let corners: Vec<(Vertex, f64, f64)> = self.hexagons.iter()
+            .flat_map(|hexagon| hexagon.hex.vertices())
+            .map(|vertex| {
+                let (x, y) = vertex.to_pixel();
+                (vertex, origin_x + spacing * x, origin_y + spacing * y)
+            })
+            .collect();
+        for node in self.nodes.iter_mut() {
+            node.vertex = corners.iter()
+                .find(|(_, x, y)| (x - node.loc.x).abs() < 1.0 && (y - node.loc.y).abs() < 1.0)
+                .map(|&(vertex, _, _)| vertex)
+                .ok_or_else(|| String::from("A node is not on the corner of any hexagon."))?;
+        }
+
+        for road in self.roads.iter_mut() {
+            let (n1, n2) = road.inds;
+            match (self.nodes.get(n1), self.nodes.get(n2)) {
+                (Some(from), Some(to)) => road.edge = Edge::between(from.vertex, to.vertex),
+                _ => return Err(String::from("A road ends at a node that doesn't exist."))
             }
-        );
+        }
 
-        // Winnow roads down to a unique set
-        self.roads = self.roads.drain(..).fold(
-            Vec::<Road>::new(),
-            | mut acc, cv | {
-                let mut reversibly_unique = true;
-                for a in &acc {
-                    if cv.inds.0 == a.inds.0 && cv.inds.1 == a.inds.1 { reversibly_unique = false };
-                    if cv.inds.0 == a.inds.1 && cv.inds.1 == a.inds.0 { reversibly_unique = false };
-                }
-                match reversibly_unique {
-                    true => { acc.push(cv); acc }
-                    false => acc
-                }
+        Ok(())
+    }
+
+    fn build_adjacency(&mut self) {
+        let node_indices: HashMap<Vertex, usize> = self.nodes.iter().enumerate()
+            .map(|(idx, node)| (node.vertex, idx))
+            .collect();
+        let hex_nodes: Vec<[usize; 6]> = self.hexagons.iter()
+            .map(|hexagon| hexagon.hex.vertices().map(|vertex| node_indices[&vertex]))
+            .collect();
+
+        let mut node_hexes = vec![Vec::new(); self.nodes.len()];
+        for (hex_idx, nodes) in hex_nodes.iter().enumerate() {
+            for &node in nodes { node_hexes[node].push(hex_idx); }
+        }
+
+        let mut node_roads = vec![Vec::new(); self.nodes.len()];
+        let mut node_nodes = vec![Vec::new(); self.nodes.len()];
+        for (road_idx, road) in self.roads.iter().enumerate() {
+            let (n1, n2) = road.inds;
+            node_roads[n1].push(road_idx);
+            node_roads[n2].push(road_idx);
+            node_nodes[n1].push(n2);
+            node_nodes[n2].push(n1);
+        }
+
+        // Draw every hexagon from the nodes themselves so shared corners are exactly the same point
+        for (hexagon, nodes) in self.hexagons.iter_mut().zip(&hex_nodes) {
+            hexagon.vertices = nodes.iter().map(|&node| self.nodes[node].loc.clone()).collect();
+        }
+
+        self.adjacency = Adjacency { hex_nodes, node_hexes, node_roads, node_nodes };
+    }
+
+    fn place_harbors<R: Rng>(&mut self, rng: &mut R) {
+        // Coastal roads only have one hexagon next to them
+        let mut coast: Vec<usize> = (0..self.roads.len())
+            .filter(|&road| self.count_bordering_hexagons(road) == 1)
+            .collect();
+        coast.shuffle(rng);
+
+        // The canonical 19 hexagon board has four generic harbors and one for each resource
+        let num_harbors = f64::round(9.0 * self.hexagons.len() as f64 / 19.0) as usize;
+        let kinds: Vec<Option<Resource>> = [
+            None,
+            None,
+            None,
+            None,
+            Some(Resource::Block),
+            Some(Resource::Rock),
+            Some(Resource::Timber),
+            Some(Resource::Fiber),
+            Some(Resource::Cereal)
+        ].iter().cycle().take(num_harbors).copied().collect();
+
+        // Harbors don't share nodes, so each one is its own place worth building toward
+        let mut used_nodes = Vec::new();
+        for road in coast {
+            if self.harbors.len() == kinds.len() { break; }
+            let (n1, n2) = self.roads[road].inds;
+            if used_nodes.contains(&n1) || used_nodes.contains(&n2) { continue; }
+            used_nodes.extend([n1, n2]);
+            let resource = kinds[self.harbors.len()];
+            let ratio = if resource.is_some() { 2 } else { 3 };
+            self.harbors.push(Harbor { road, resource, ratio });
+        }
+    }
+
+    /// Generic harbors are one better than the bank, whatever the house rules make the bank
+    pub fn price_generic_harbors(&mut self, bank_ratio: u16) {
+        for harbor in self.harbors.iter_mut().filter(|harbor| harbor.resource.is_none()) {
+            harbor.ratio = bank_ratio.saturating_sub(1).max(1);
+        }
+    }
+
+    fn count_bordering_hexagons(&self, road_idx: usize) -> usize {
+        let (n1, n2) = self.roads[road_idx].inds;
+        let other_side = self.find_neighboring_hexagons(n2);
+        self.find_neighboring_hexagons(n1).iter()
+            .filter(|hex| other_side.contains(hex))
+            .count()
+    }
+
+    /// The best rate a player can get for `resource`, from the bank or from their harbors
+    pub fn trade_ratio(&self, key: &str, resource: Resource, bank_ratio: u16) -> u16 {
+        self.harbors.iter().fold(
+            bank_ratio,
+            | best, harbor | {
+                let (n1, n2) = self.roads[harbor.road].inds;
+                let owned = [n1, n2].iter().any(|&n| self.nodes[n].player_key.as_deref() == Some(key));
+                let fits = harbor.resource.is_none() || harbor.resource == Some(resource);
+                if owned && fits { best.min(harbor.ratio) } else { best }
             }
-        );
+        )
     }
 
     pub fn find_neighboring_nodes(&self, hex_idx: usize) -> Vec<usize> {
-        let neighboring_nodes_indices = self.nodes.iter().enumerate().fold(
-            Vec::new(),
-            | mut acc, (n,cv) | {
-                let any_matches = self.hexagons[hex_idx].vertices.iter().any(
-                    | v | {
-                        v.x == cv.loc.x && v.y == cv.loc.y
-                    }
-                );
-                if any_matches { acc.push(n) }
-                acc
-            }
-        );
+        let mut neighboring_nodes_indices = self.adjacency.hex_nodes[hex_idx].to_vec();
+        neighboring_nodes_indices.sort_unstable();
 
         neighboring_nodes_indices
     }
 
     pub fn find_neighboring_hexagons(&self, node_idx: usize) -> Vec<usize> {
-        let neighboring_hexagon_indices = self.hexagons.iter().enumerate().fold(
-            Vec::new(),
-            | mut acc, (n,cv) | {
-                let any_matches = cv.vertices.iter().any(
-                    | v | {
-                        v.x == self.nodes[node_idx].loc.x && 
-                        v.y == self.nodes[node_idx].loc.y
-                    }
-                );
-                if any_matches { acc.push(n) }
-                acc
-            }
-        );
-
-        neighboring_hexagon_indices
+        self.adjacency.node_hexes[node_idx].clone()
     }
 
     pub fn resolve_setup(&self) -> Vec<(String,Resource)> {
@@ -334,9 +575,10 @@ impl GameBoard {
         for (ind, resource) in rolled_hexagons {
             let neighboring_nodes = self.find_neighboring_nodes(ind);
             for nn in neighboring_nodes {
-                match &self.nodes[nn].player_key {
-                    Some(player) => spoils.push( (player.clone(), resource) ),
-                    None => ()
+                if let Some(player) = &self.nodes[nn].player_key {
+                    // Towns produce twice as much as villages
+                    let amount = if self.nodes[nn].building_type == BuildingType::Town { 2 } else { 1 };
+                    for _ in 0..amount { spoils.push( (player.clone(), resource) ); }
                 }
             }
         }
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+use crate::games::core::error::{ GameError, ErrorCode };
+use crate::games::hexagon::resources::Resource;
+use super::{ MapTile, Hex };
+
+/// How the numbers are spread over the board once the resources are down
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum NumberPlacement {
+    /// A plain shuffle
+    #[default]
+    Random,
+    /// Shuffled, then rearranged so no 6s and 8s ever touch and each resource gets a spread of numbers
+    Balanced,
+    /// The standard sequence laid in a spiral from a corner, skipping deserts
+    Spiral
+}
+
+// The order the numbers are laid out in for the standard spiral
+const SPIRAL_SEQUENCE: [u8; 18] = [5, 2, 6, 3, 8, 10, 9, 12, 11, 4, 8, 10, 9, 4, 5, 6, 3, 11];
+
+// Enough swaps to settle any board that fits in a game
+const BALANCING_SWAPS: usize = 5000;
+
+// How many places the search for spots to keep 6s and 8s apart tries before giving up on a map
+const SEARCH_STEPS: usize = 100_000;
+
+fn is_hot(number: u8) -> bool {
+    number == 6 || number == 8
+}
+
+/// Which tiles share an edge with each tile
+pub fn find_adjacent_tiles(tiles: &[MapTile]) -> Vec<Vec<usize>> {
+    let indices: HashMap<Hex, usize> = tiles.iter().enumerate().map(|(idx, tile)| (tile.hex(), idx)).collect();
+    tiles.iter().map(
+        | tile | tile.hex().neighbors().iter().filter_map(|hex| indices.get(hex).copied()).collect()
+    ).collect()
+}
+
+/// How far a board is from balanced; touching 6s and 8s count far more than anything else
+pub fn imbalance(adjacent: &[Vec<usize>], resources: &[Resource], numbers: &[u8]) -> u32 {
+    let mut penalty = 0;
+
+    for (idx, neighbors) in adjacent.iter().enumerate() {
+        for &other in neighbors.iter().filter(|&&other| other > idx) {
+            if is_hot(numbers[idx]) && is_hot(numbers[other]) { penalty += 100; }
+            else if numbers[idx] == numbers[other] && numbers[idx] != 1 { penalty += 10; }
+        }
+    }
+
+    // Each resource should come up on as many different rolls as it can
+    let mut seen = [[false; 13]; 6];
+    for (&rsc, &num) in resources.iter().zip(numbers) {
+        if rsc == Resource::Desert { continue; }
+        let seen = &mut seen[rsc as usize][usize::from(num.min(12))];
+        if *seen { penalty += 1; } else { *seen = true; }
+    }
+
+    penalty
+}
+
+fn touches_hot(adjacent: &[Vec<usize>], numbers: &[u8], idx: usize) -> bool {
+    is_hot(numbers[idx]) && adjacent[idx].iter().any(|&other| is_hot(numbers[other]))
+}
+
+/// Moves the 6s and 8s on the `movable` tiles apart, then swaps numbers between them until the board
+/// is balanced or nothing more can be done; fails when the map leaves no way to keep 6s and 8s apart
+pub fn balance<R: Rng>(
+    tiles: &[MapTile],
+    resources: &[Resource],
+    numbers: &mut [u8],
+    movable: &[usize],
+    rng: &mut R
+) -> Result<(), GameError> {
+    let adjacent = find_adjacent_tiles(tiles);
+    separate_hot_numbers(&adjacent, numbers, movable, rng)?;
+    if movable.len() < 2 { return Ok(()); }
+
+    let mut penalty = imbalance(&adjacent, resources, numbers);
+    for _ in 0..BALANCING_SWAPS {
+        if penalty == 0 { break; }
+
+        let a = movable[rng.gen_range(0..movable.len())];
+        let b = movable[rng.gen_range(0..movable.len())];
+        if numbers[a] == numbers[b] { continue; }
+
+        // Keep sideways moves too, so the search can wander off a plateau, but never put 6s and 8s back together
+        numbers.swap(a, b);
+        let new_penalty = imbalance(&adjacent, resources, numbers);
+        if new_penalty <= penalty && !touches_hot(&adjacent, numbers, a) && !touches_hot(&adjacent, numbers, b) {
+            penalty = new_penalty;
+        }
+        else { numbers.swap(a, b); }
+    }
+
+    Ok(())
+}
+
+// Deals the 6s and 8s on the `movable` tiles out to spots where none of them touch
+fn separate_hot_numbers<R: Rng>(
+    adjacent: &[Vec<usize>],
+    numbers: &mut [u8],
+    movable: &[usize],
+    rng: &mut R
+) -> Result<(), GameError> {
+    let impossible = || GameError::new(ErrorCode::InvalidConfig, "There is no way to keep the 6s and 8s apart on this map.");
+
+    // Whatever the map numbered itself stays put, so nothing can go next to its 6s and 8s
+    let fixed_hot: Vec<usize> = (0..numbers.len())
+        .filter(|&idx| is_hot(numbers[idx]) && !movable.contains(&idx))
+        .collect();
+    if fixed_hot.iter().any(|&idx| adjacent[idx].iter().any(|other| fixed_hot.contains(other))) {
+        return Err(impossible());
+    }
+    let (hot, cold): (Vec<u8>, Vec<u8>) = movable.iter().map(|&idx| numbers[idx]).partition(|&num| is_hot(num));
+
+    let mut spots: Vec<usize> = movable.iter().copied()
+        .filter(|&idx| !adjacent[idx].iter().any(|other| fixed_hot.contains(other)))
+        .collect();
+    spots.shuffle(rng);
+    let mut chosen = Vec::new();
+    let mut steps = 0;
+    if !spread_out(adjacent, &spots, hot.len(), &mut chosen, &mut steps) { return Err(impossible()); }
+
+    let (mut hot, mut cold) = (hot.into_iter(), cold.into_iter());
+    for &idx in movable {
+        let next = if chosen.contains(&idx) { hot.next() } else { cold.next() };
+        if let Some(num) = next { numbers[idx] = num; }
+    }
+
+    Ok(())
+}
+
+// Picks `count` of the `spots` with no two next to each other, backing up whenever it runs out of room
+fn spread_out(adjacent: &[Vec<usize>], spots: &[usize], count: usize, chosen: &mut Vec<usize>, steps: &mut usize) -> bool {
+    if chosen.len() == count { return true; }
+    if spots.len() < count - chosen.len() || *steps >= SEARCH_STEPS { return false; }
+    *steps += 1;
+
+    // Either the first spot is taken, which rules out its neighbors, or it isn't
+    let (&first, rest) = match spots.split_first() {
+        Some(split) => split,
+        None => return false
+    };
+    let open: Vec<usize> = rest.iter().copied().filter(|spot| !adjacent[first].contains(spot)).collect();
+    chosen.push(first);
+    if spread_out(adjacent, &open, count, chosen, steps) { return true; }
+    chosen.pop();
+
+    spread_out(adjacent, rest, count, chosen, steps)
+}
+
+/// The `movable` tiles from the outside in, going around each ring counter-clockwise from a random corner
+pub fn spiral_order<R: Rng>(tiles: &[MapTile], movable: &[usize], rng: &mut R) -> Vec<usize> {
+    if tiles.is_empty() { return Vec::new(); }
+
+    // Spiral in on whichever tile sits closest to the middle of the map
+    let (sum_q, sum_r) = tiles.iter().fold((0.0, 0.0), |(q, r), tile| (q + f64::from(tile.q), r + f64::from(tile.r)));
+    let (mean_q, mean_r) = (sum_q / tiles.len() as f64, sum_r / tiles.len() as f64);
+    let middle = tiles.iter().min_by(
+        | a, b | {
+            let dist = | t: &&MapTile | (f64::from(t.q) - mean_q).powi(2) + (f64::from(t.r) - mean_r).powi(2);
+            dist(a).total_cmp(&dist(b))
+        }
+    ).unwrap();
+
+    let ring = | tile: &MapTile | tile.hex().distance(middle.hex());
+    let start = f64::from(rng.gen_range(0..6u8)) * std::f64::consts::PI / 3.0;
+    let angle = | tile: &MapTile | {
+        let (x, y) = Hex::new(tile.q - middle.q, tile.r - middle.r).to_pixel();
+        (f64::atan2(-y, x) - start).rem_euclid(2.0 * std::f64::consts::PI)
+    };
+
+    let mut order = movable.to_vec();
+    order.sort_by(
+        | &a, &b | ring(&tiles[b]).cmp(&ring(&tiles[a]))
+            .then(angle(&tiles[a]).total_cmp(&angle(&tiles[b])))
+    );
+
+    order
+}
+
+/// The standard sequence, over again as many times as the board needs
+pub fn spiral_numbers(count: usize) -> Vec<u8> {
+    SPIRAL_SEQUENCE.iter().cycle().take(count).copied().collect()
+}
@@ -1,11 +1,15 @@
 use super::*;
+use rand::thread_rng;
 use crate::games::hexagon::actions::roll_dice;
 use crate::games::hexagon::resources::{ Resource, ResourceList };
+use crate::games::core::error::ErrorCode;
+use crate::games::hexagon::GameRng;
+use rand::SeedableRng;
 
 #[test]
 fn board_setup() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     assert_eq!(board.centroids.len(), 19);
     assert_eq!(board.hexagons.len(), 19);
@@ -83,7 +87,7 @@ fn board_setup() {
 #[test]
 fn should_find_neighboring_nodes() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     let hex_idx = 0;
     let node_indices = board.find_neighboring_nodes(hex_idx);
@@ -97,7 +101,7 @@ fn should_find_neighboring_nodes() {
 #[test]
 fn should_find_neighboring_hexagons() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     let node_idx = 0;
     let hexagon_indices = board.find_neighboring_hexagons(node_idx);
@@ -107,7 +111,7 @@ fn should_find_neighboring_hexagons() {
 #[test]
 fn should_collect_rolled_resources() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     // Build a village on each node
     let player_key = String::from("key_1");
@@ -117,7 +121,7 @@ fn should_collect_rolled_resources() {
     }
 
     // Roll the dice
-    let roll_result = roll_dice();
+    let roll_result = roll_dice(&mut thread_rng());
     let roll_sum = roll_result.0 + roll_result.1;
 
     // Find out what resources were rolled
@@ -165,7 +169,7 @@ fn should_collect_rolled_resources() {
 #[test]
 fn scorpion_should_block_resources() {
     let mut board = GameBoard::new();
-    board.setup(5);
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     // Build a village on each node
     let player_key = String::from("key_1");
@@ -227,4 +231,350 @@ fn scorpion_should_block_resources() {
             _ => ()
         }
     }    
-}
\ No newline at end of file
+}
+#[test]
+fn split_layout_from_state() {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+    board.nodes[3].player_key = Some(String::from("key1"));
+    board.nodes[3].building_type = BuildingType::Village;
+    board.roads[7].player_key = Some(String::from("key1"));
+
+    let layout = board.get_layout();
+    assert_eq!(layout.hexagons, board.hexagons);
+    assert_eq!(layout.nodes.len(), board.nodes.len());
+    assert_eq!(layout.roads[7], board.roads[7].inds);
+
+    let state = board.get_state(|key| if key == "key1" { Some(0) } else { None });
+    assert_eq!(state.nodes.len(), layout.nodes.len());
+    assert_eq!(state.roads.len(), layout.roads.len());
+    assert_eq!(state.nodes[3], NodeState { owner: Some(0), building_type: BuildingType::Village });
+    assert_eq!(state.roads[7], Some(0));
+    assert_eq!(state.roads[8], None);
+    assert_eq!(state.scorpion_index, board.scorpion_index);
+
+    // Building doesn't touch the layout
+    board.roads[8].player_key = Some(String::from("key1"));
+    assert_eq!(board.get_layout(), layout);
+}
+
+#[test]
+fn harbors_on_the_coast() {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+
+    assert_eq!(board.harbors.len(), 9);
+    assert_eq!(board.harbors.iter().filter(|h| h.resource.is_none() && h.ratio == 3).count(), 4);
+    assert_eq!(board.harbors.iter().filter(|h| h.resource.is_some() && h.ratio == 2).count(), 5);
+
+    let mut harbor_nodes = Vec::new();
+    for harbor in &board.harbors {
+        assert_eq!(board.count_bordering_hexagons(harbor.road), 1);
+        let (n1, n2) = board.roads[harbor.road].inds;
+        harbor_nodes.extend([n1, n2]);
+    }
+    let num_harbor_nodes = harbor_nodes.len();
+    harbor_nodes.sort_unstable();
+    harbor_nodes.dedup();
+    assert_eq!(harbor_nodes.len(), num_harbor_nodes);
+
+    assert_eq!(board.get_layout().harbors, board.harbors);
+}
+
+#[test]
+fn trade_ratio_from_harbors() {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+    assert_eq!(board.trade_ratio("key1", Resource::Block, 4), 4);
+
+    let generic = board.harbors.iter().find(|h| h.resource.is_none()).unwrap().road;
+    board.nodes[board.roads[generic].inds.0].player_key = Some(String::from("key1"));
+    assert_eq!(board.trade_ratio("key1", Resource::Block, 4), 3);
+    assert_eq!(board.trade_ratio("key2", Resource::Block, 4), 4);
+
+    let specific = board.harbors.iter().find(|h| h.resource == Some(Resource::Rock)).unwrap().road;
+    board.nodes[board.roads[specific].inds.1].player_key = Some(String::from("key1"));
+    assert_eq!(board.trade_ratio("key1", Resource::Rock, 4), 2);
+    assert_eq!(board.trade_ratio("key1", Resource::Block, 4), 3);
+}
+
+#[test]
+fn towns_produce_double() {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+
+    let hex_idx = board.hexagons.iter().position(|h| h.resource != Resource::Desert).unwrap();
+    let roll_sum = board.hexagons[hex_idx].number;
+    let nodes = board.find_neighboring_nodes(hex_idx);
+    board.nodes[nodes[0]].player_key = Some(String::from("key1"));
+    board.nodes[nodes[0]].building_type = BuildingType::Village;
+    let village_spoils = board.resolve_roll(roll_sum).iter().filter(|(k, _)| k == "key1").count();
+
+    board.nodes[nodes[0]].building_type = BuildingType::Town;
+    let town_spoils = board.resolve_roll(roll_sum).iter().filter(|(k, _)| k == "key1").count();
+    assert_eq!(town_spoils, 2 * village_spoils);
+    assert!(village_spoils > 0);
+}
+
+#[test]
+fn builtin_maps() {
+    for name in BUILTIN_MAPS {
+        let map = BoardMap::builtin(name).unwrap();
+        assert_eq!(map.name, name);
+        assert_eq!(map.validate(), Ok(()));
+
+        let mut board = GameBoard::new();
+        board.setup(&map, NumberPlacement::Random, &mut thread_rng()).unwrap();
+        assert_eq!(board.hexagons.len(), map.tiles.len());
+        assert!(board.hexagons.iter().all(|h| h.vertices.len() == 6));
+        assert!(board.hexagons.iter().all(
+            |h| if h.resource == Resource::Desert { h.number == 1 } else { (2..=12).contains(&h.number) && h.number != 7 }
+        ));
+        assert!(!board.harbors.is_empty());
+    }
+    assert_eq!(BoardMap::builtin("atlantis"), None);
+
+    // The classic map is the same island the default width gives
+    assert_eq!(BoardMap::builtin("classic").unwrap().tiles, BoardMap::hexagon(5).tiles);
+    assert_eq!(BoardMap::builtin("rectangle").unwrap().tiles.len(), 24);
+}
+
+#[test]
+fn islands_stay_apart() {
+    let map = BoardMap::builtin("archipelago").unwrap();
+    let mut board = GameBoard::new();
+    board.setup(&map, NumberPlacement::Random, &mut thread_rng()).unwrap();
+
+    // The map puts the desert in the middle of the home island
+    assert_eq!(board.hexagons[3].resource, Resource::Desert);
+    assert_eq!(board.scorpion_index, Some(3));
+
+    let home: Vec<usize> = (0..7).flat_map(|idx| board.find_neighboring_nodes(idx)).collect();
+    for idx in 7..map.tiles.len() {
+        assert!(board.find_neighboring_nodes(idx).iter().all(|node| !home.contains(node)));
+    }
+}
+
+#[test]
+fn fixed_tiles_from_a_map() {
+    let map: BoardMap = serde_json::from_str(r#"{
+        "tiles": [
+            {"q": 0, "r": 0, "resource": "Rock", "number": 6},
+            {"q": 1, "r": 0, "resource": "Timber"},
+            {"q": 0, "r": 1},
+            {"q": 5, "r": 5}
+        ]
+    }"#).unwrap();
+    assert_eq!(map.validate(), Ok(()));
+
+    let mut board = GameBoard::new();
+    board.setup(&map, NumberPlacement::Random, &mut thread_rng()).unwrap();
+    assert_eq!((board.hexagons[0].resource, board.hexagons[0].number), (Resource::Rock, 6));
+    assert_eq!(board.hexagons[1].resource, Resource::Timber);
+    assert_eq!(board.hexagons.iter().filter(|h| h.resource == Resource::Desert).count(), 1);
+
+    // Three touching hexagons share nodes; the one off on its own doesn't
+    assert_eq!(board.nodes.len(), 13 + 6);
+    assert_eq!(board.roads.len(), 15 + 6);
+
+    let bad_tiles = [
+        r#"{"tiles": []}"#,
+        r#"{"tiles": [{"q": 0, "r": 0}, {"q": 0, "r": 0}]}"#,
+        r#"{"tiles": [{"q": 0, "r": 0, "number": 6}]}"#,
+        r#"{"tiles": [{"q": 0, "r": 0, "resource": "Desert", "number": 6}]}"#,
+        r#"{"tiles": [{"q": 0, "r": 0, "resource": "Rock", "number": 7}]}"#,
+        r#"{"tiles": [{"q": 32767, "r": 0}]}"#,
+        r#"{"tiles": [{"q": 0, "r": -32768}]}"#,
+        r#"{"tiles": [{"q": 10, "r": 10}]}"#
+    ];
+    for json in bad_tiles {
+        let map: BoardMap = serde_json::from_str(json).unwrap();
+        assert_eq!(map.validate().unwrap_err().code, ErrorCode::InvalidConfig, "{}", json);
+    }
+}
+
+fn touching_pairs(board: &GameBoard, map: &BoardMap) -> Vec<(u8,u8)> {
+    let adjacent = numbers::find_adjacent_tiles(&map.tiles);
+    adjacent.iter().enumerate().flat_map(
+        | (idx, neighbors) | neighbors.iter().map(move |&other| (board.hexagons[idx].number, board.hexagons[other].number))
+    ).collect()
+}
+
+#[test]
+fn balanced_numbers() {
+    let maps = [BoardMap::hexagon(5), BoardMap::hexagon(7), BoardMap::builtin("rectangle").unwrap(), BoardMap::builtin("archipelago").unwrap()];
+    for map in maps {
+        for seed in 0..50 {
+            let mut board = GameBoard::new();
+            board.setup(&map, NumberPlacement::Balanced, &mut GameRng::seed_from_u64(seed)).unwrap();
+
+            for (a, b) in touching_pairs(&board, &map) {
+                assert!(!((a == 6 || a == 8) && (b == 6 || b == 8)), "{} touches {} on {}", a, b, map.name);
+                assert!(a != b || a == 1);
+            }
+            assert!(board.hexagons.iter().all(|h| (h.resource == Resource::Desert) == (h.number == 1)));
+        }
+    }
+
+    // The biggest boards are crowded with 6s and 8s, but they still never touch
+    let map = BoardMap::hexagon(15);
+    for seed in 0..5 {
+        let mut board = GameBoard::new();
+        board.setup(&map, NumberPlacement::Balanced, &mut GameRng::seed_from_u64(seed)).unwrap();
+        assert!(touching_pairs(&board, &map).iter().all(|&(a, b)| !((a == 6 || a == 8) && (b == 6 || b == 8))));
+    }
+
+    // The numbers are only moved around, not changed
+    let mut random = GameBoard::new();
+    random.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut GameRng::seed_from_u64(7)).unwrap();
+    let mut balanced = GameBoard::new();
+    balanced.setup(&BoardMap::hexagon(5), NumberPlacement::Balanced, &mut GameRng::seed_from_u64(7)).unwrap();
+    let sorted_numbers = | board: &GameBoard | {
+        let mut numbers: Vec<u8> = board.hexagons.iter().map(|h| h.number).collect();
+        numbers.sort_unstable();
+        numbers
+    };
+    assert_eq!(sorted_numbers(&random), sorted_numbers(&balanced));
+    assert_eq!(
+        random.hexagons.iter().map(|h| h.resource).collect::<Vec<_>>(),
+        balanced.hexagons.iter().map(|h| h.resource).collect::<Vec<_>>()
+    );
+
+    // A map that puts its own 6 and 8 side by side can't be balanced, so it is turned down
+    let map: BoardMap = serde_json::from_str(r#"{
+        "tiles": [
+            {"q": 0, "r": 0, "resource": "Rock", "number": 6},
+            {"q": 1, "r": 0, "resource": "Timber", "number": 8},
+            {"q": 0, "r": 1},
+            {"q": 1, "r": 1}
+        ]
+    }"#).unwrap();
+    assert_eq!(
+        GameBoard::new().setup(&map, NumberPlacement::Balanced, &mut thread_rng()).unwrap_err().code,
+        ErrorCode::InvalidConfig
+    );
+    assert!(GameBoard::new().setup(&map, NumberPlacement::Random, &mut thread_rng()).is_ok());
+}
+
+#[test]
+fn spiral_numbers() {
+    let map = BoardMap::hexagon(5);
+    let all: Vec<usize> = (0..map.tiles.len()).collect();
+    let order = numbers::spiral_order(&map.tiles, &all, &mut thread_rng());
+
+    // Around the outside, then the inner ring, then the middle, one neighbor at a time
+    let adjacent = numbers::find_adjacent_tiles(&map.tiles);
+    assert_eq!(order[18], 9);
+    for step in order.windows(2).take(17) {
+        assert!(adjacent[step[0]].contains(&step[1]));
+    }
+    assert_eq!(adjacent[order[0]].len(), 3);
+
+    for seed in 0..20 {
+        let mut board = GameBoard::new();
+        board.setup(&map, NumberPlacement::Spiral, &mut GameRng::seed_from_u64(seed)).unwrap();
+        let mut numbers: Vec<u8> = board.hexagons.iter().map(|h| h.number).filter(|&n| n != 1).collect();
+        numbers.sort_unstable();
+        assert_eq!(numbers, [2, 3, 3, 4, 4, 5, 5, 6, 6, 8, 8, 9, 9, 10, 10, 11, 11, 12]);
+        for (a, b) in touching_pairs(&board, &map) {
+            assert!(!((a == 6 || a == 8) && (b == 6 || b == 8)));
+        }
+    }
+}
+
+#[test]
+fn hex_coordinates() {
+    let origin = Hex::new(0, 0);
+    assert_eq!(origin.s(), 0);
+    assert_eq!(Hex::new(2, -3).s(), 1);
+    assert!(origin.neighbors().iter().all(|&hex| origin.distance(hex) == 1));
+    assert_eq!(origin.distance(Hex::new(2, -3)), 3);
+
+    // Neighbors share the two corners at either end of the side between them
+    let east = Hex::new(1, 0);
+    let shared: Vec<Vertex> = origin.vertices().into_iter().filter(|v| east.vertices().contains(v)).collect();
+    assert_eq!(shared.len(), 2);
+    let side = Edge::between(shared[0], shared[1]);
+    assert!(origin.edges().iter().any(|&(a, b)| Edge::between(a, b) == side));
+    assert!(east.edges().iter().any(|&(a, b)| Edge::between(b, a) == side));
+
+    // Three hexagons meet at each corner
+    let corner = origin.vertices()[1];
+    assert!(Hex::new(0, 1).vertices().contains(&corner));
+    assert!(east.vertices().contains(&corner));
+    assert!(!Hex::new(-1, 0).vertices().contains(&corner));
+}
+
+#[test]
+fn adjacency_tables() {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+    let adjacency = &board.adjacency;
+
+    assert_eq!(adjacency.hex_nodes.len(), board.hexagons.len());
+    assert_eq!(adjacency.node_hexes.len(), board.nodes.len());
+    assert_eq!(adjacency.node_roads.len(), board.nodes.len());
+    assert_eq!(adjacency.node_nodes.len(), board.nodes.len());
+
+    for (hex_idx, nodes) in adjacency.hex_nodes.iter().enumerate() {
+        for (corner, &node) in nodes.iter().enumerate() {
+            assert_eq!(board.nodes[node].vertex, board.hexagons[hex_idx].hex.vertices()[corner]);
+            assert_eq!(board.nodes[node].loc, board.hexagons[hex_idx].vertices[corner]);
+            assert!(adjacency.node_hexes[node].contains(&hex_idx));
+        }
+    }
+
+    for (road_idx, road) in board.roads.iter().enumerate() {
+        let (n1, n2) = road.inds;
+        assert_eq!(road.edge, Edge::between(board.nodes[n1].vertex, board.nodes[n2].vertex));
+        assert!(adjacency.node_roads[n1].contains(&road_idx) && adjacency.node_roads[n2].contains(&road_idx));
+        assert!(adjacency.node_nodes[n1].contains(&n2) && adjacency.node_nodes[n2].contains(&n1));
+    }
+
+    // Corners on the coast touch one or two hexagons and two or three roads, inland ones three of each
+    assert!(adjacency.node_hexes.iter().all(|hexes| (1..=3).contains(&hexes.len())));
+    assert!(adjacency.node_roads.iter().all(|roads| (2..=3).contains(&roads.len())));
+    assert_eq!(adjacency.node_hexes.iter().filter(|hexes| hexes.len() == 3).count(), 24);
+}
+
+#[test]
+fn board_sizes() {
+    for width in (3..=15u8).step_by(2) {
+        let mut board = GameBoard::new();
+        board.setup(&BoardMap::hexagon(width), NumberPlacement::Random, &mut thread_rng()).unwrap();
+
+        // A hexagon n rings out from the middle
+        let n = usize::from(width - 1) / 2;
+        assert_eq!(board.hexagons.len(), 3 * n * n + 3 * n + 1);
+        assert_eq!(board.nodes.len(), 6 * (n + 1) * (n + 1));
+        assert_eq!(board.roads.len(), 9 * n * n + 15 * n + 6);
+    }
+}
+
+#[test]
+fn boards_from_snapshots() {
+    for map in [BoardMap::hexagon(5), BoardMap::builtin("rectangle").unwrap(), BoardMap::builtin("archipelago").unwrap()] {
+        let mut board = GameBoard::new();
+        board.setup(&map, NumberPlacement::Random, &mut thread_rng()).unwrap();
+
+        // The adjacency tables aren't saved but come back all the same
+        let snapshot = serde_json::to_value(&board).unwrap();
+        assert!(snapshot.get("adjacency").is_none());
+        assert_eq!(serde_json::from_value::<GameBoard>(snapshot.clone()).unwrap(), board);
+
+        // Older snapshots don't have coordinates either, so they get worked out from the pixels
+        let mut old = snapshot;
+        for (list, key) in [("hexagons", "hex"), ("nodes", "vertex"), ("roads", "edge")] {
+            for item in old[list].as_array_mut().unwrap() {
+                item.as_object_mut().unwrap().remove(key);
+            }
+        }
+        let loaded: GameBoard = serde_json::from_value(old).unwrap();
+        assert_eq!(loaded.adjacency, board.adjacency);
+        assert_eq!(loaded.hexagons, board.hexagons.iter().map(|hexagon| {
+            // Only where the board sits can be different
+            let shift = (loaded.hexagons[0].hex.q - board.hexagons[0].hex.q, loaded.hexagons[0].hex.r - board.hexagons[0].hex.r);
+            Hexagon { hex: Hex::new(hexagon.hex.q + shift.0, hexagon.hex.r + shift.1), ..hexagon.clone() }
+        }).collect::<Vec<Hexagon>>());
+    }
+}
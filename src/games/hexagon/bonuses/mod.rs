@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use crate::games::core::playe::Players;
+use super::board::GameBoard;
+
+#[cfg(test)]
+mod test;
+
+pub fn find_most_bugs(bugs: &HashMap<String, u8>, title_holder: &Option<String>, threshold: u8) -> Option<String> {
+
+    let ( has_most_bugs, most_bugs ) = bugs.iter().fold(
+        ( None, 0 ),
+        | acc, ( key, val ) | {
+            if *val > acc.1 {
+                return ( Some(key.clone()), *val );
+            } else {
+                return acc;
+            }
+        }
+    );
+
+    match has_most_bugs {
+        Some(key) => {
+            match title_holder {
+                Some(champ) => {
+                    if let Some(champ_bugs) = bugs.get(champ) {
+                        if most_bugs > *champ_bugs { return Some(key); }
+                        else { return Some(champ.clone()); }
+                    } else {
+                        return Some(key);
+                    }
+                },
+                None => {
+                    if most_bugs >= threshold { return Some(key); }
+                    else { return None; }
+                }
+            }
+        },
+        None => { return None; }
+    }
+}
+
+pub fn find_longest_road(board: &GameBoard, players: &Players, title_holder: &Option<String>, threshold: u8) -> Option<String> {
+
+    let road_lengths: HashMap<String,u8> = get_road_lengths(board, players);
+
+    let ( has_longest_road, longest_road ) = road_lengths.iter().fold(
+        ( None, 0 ),
+        | acc, ( key, val ) | {
+            if *val > acc.1 {
+                return ( Some(key.clone()), *val );
+            } else {
+                return acc;
+            }
+        }
+    );
+
+    match has_longest_road {
+        Some(key) => {
+            match title_holder {
+                Some(champ) => {
+                    if let Some(champ_road) = road_lengths.get(champ) {
+                        if longest_road > *champ_road { return Some(key); }
+                        else { return Some(champ.clone()); }
+                    } else {
+                        return Some(key);
+                    }
+                },
+                None => {
+                    if longest_road >= threshold { return Some(key); }
+                    else { return None; }
+                }
+            }
+        },
+        None => { return None; }
+    }
+}
+
+fn get_road_lengths(board: &GameBoard, players: &Players) -> HashMap<String,u8> {
+    players.list.iter().fold(
+        HashMap::<String,u8>::new(),
+        | mut acc, plyr | {
+            acc.insert(plyr.key.clone(), measure_longest_road(board, &plyr.key));
+            acc
+        }
+    )
+}
+
+/// The most roads a player can follow in one go without using any of them twice;
+/// another player's building on a node ends the road there
+pub fn measure_longest_road(board: &GameBoard, key: &str) -> u8 {
+    let owned: Vec<bool> = board.roads.iter()
+        .map(|road| road.player_key.as_deref() == Some(key))
+        .collect();
+    let blocked: Vec<bool> = board.nodes.iter()
+        .map(|node| node.player_key.as_deref().is_some_and(|owner| owner != key))
+        .collect();
+    let degrees: Vec<usize> = board.adjacency.node_roads.iter()
+        .map(|roads| roads.iter().filter(|&&road| owned[road]).count())
+        .collect();
+
+    let mut search = TrailSearch {
+        board,
+        owned: &owned,
+        blocked: &blocked,
+        used: vec![false; board.roads.len()],
+        odd: degrees.iter().map(|degree| degree % 2 == 1).collect(),
+        num_odd: degrees.iter().filter(|&&degree| degree % 2 == 1).count(),
+        num_unused: owned.iter().filter(|&&own| own).count(),
+        longest: 0,
+        budget: usize::MAX
+    };
+
+    // A longest road can always be started from a dead end, a fork or a building that cuts it,
+    // unless it runs all the way around a plain loop, so those are the only places worth starting
+    let mut starts: Vec<usize> = (0..board.nodes.len())
+        .filter(|&node| degrees[node] != 0 && (degrees[node] != 2 || blocked[node]))
+        .collect();
+    let mut visited = vec![false; board.nodes.len()];
+    for &node in &starts { search.mark_reachable(node, &mut visited); }
+    starts.extend((0..board.nodes.len()).filter(|&node| degrees[node] != 0 && !visited[node]));
+
+    // Searching from everywhere a little at a time turns up a long road early, which cuts the rest short
+    let mut budget = 64;
+    while !starts.is_empty() {
+        starts.retain(|&node| {
+            search.budget = budget;
+            search.extend(node, 0);
+            search.budget == 0
+        });
+        budget *= 4;
+    }
+
+    u8::try_from(search.longest).unwrap_or(u8::MAX)
+}
+
+// A depth first search over one player's roads that gives up on any branch that can't beat the best so far
+struct TrailSearch<'a> {
+    board: &'a GameBoard,
+    owned: &'a [bool],
+    blocked: &'a [bool],
+    used: Vec<bool>,
+    // Which nodes have an odd number of unused roads, and how many
+    odd: Vec<bool>,
+    num_odd: usize,
+    num_unused: usize,
+    longest: usize,
+    // How many more steps the search can take before it has to come back later
+    budget: usize
+}
+
+impl TrailSearch<'_> {
+    fn next_roads(&self, node: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.board.adjacency.node_roads[node].iter()
+            .filter(|&&road| self.owned[road])
+            .map(move |&road| {
+                let (a, b) = self.board.roads[road].inds;
+                (road, if a == node { b } else { a })
+            })
+    }
+
+    // Unused roads out of `node`, heading for the quietest corners first since that finds long roads sooner
+    fn ways_on(&self, node: usize) -> Vec<(usize, usize)> {
+        let unused = | node: usize | self.next_roads(node).filter(|&(road, _)| !self.used[road]);
+        let mut ways: Vec<(usize, usize)> = unused(node).collect();
+        ways.sort_by_key(|&(_, other)| unused(other).count());
+        ways
+    }
+
+    fn toggle(&mut self, road: usize) {
+        self.used[road] = !self.used[road];
+        if self.used[road] { self.num_unused -= 1; } else { self.num_unused += 1; }
+        let (a, b) = self.board.roads[road].inds;
+        for node in [a, b] {
+            self.odd[node] = !self.odd[node];
+            if self.odd[node] { self.num_odd += 1; } else { self.num_odd -= 1; }
+        }
+    }
+
+    fn extend(&mut self, node: usize, length: usize) {
+        if self.budget == 0 { return; }
+        self.budget -= 1;
+        self.longest = self.longest.max(length);
+
+        // Every node left with an odd number of unused roads at the end leaves one of them behind,
+        // and only the far end can be evened out unless the road starts from an odd one
+        let left_odd = if self.odd[node] { self.num_odd - 2 } else { self.num_odd };
+        let reachable = self.num_unused - left_odd / 2;
+        if length + reachable <= self.longest { return; }
+
+        for (road, other) in self.ways_on(node) {
+            self.toggle(road);
+            if self.blocked[other] { self.longest = self.longest.max(length + 1); }
+            else { self.extend(other, length + 1); }
+            self.toggle(road);
+        }
+    }
+
+    fn mark_reachable(&self, node: usize, visited: &mut [bool]) {
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if visited[node] { continue; }
+            visited[node] = true;
+            stack.extend(self.next_roads(node).map(|(_, other)| other).filter(|&other| !visited[other]));
+        }
+    }
+}
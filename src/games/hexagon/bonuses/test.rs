@@ -0,0 +1,152 @@
+use super::*;
+use rand::thread_rng;
+use proptest::prelude::*;
+use crate::games::hexagon::board::{ BoardMap, NumberPlacement, BuildingType };
+
+fn new_board() -> GameBoard {
+    let mut board = GameBoard::new();
+    board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
+    board
+}
+
+fn road_between(board: &GameBoard, a: usize, b: usize) -> usize {
+    *board.adjacency.node_roads[a].iter()
+        .find(|&&road| board.adjacency.node_roads[b].contains(&road))
+        .unwrap()
+}
+
+// Claims `count` sides of a hexagon going around from its first corner
+fn claim_sides(board: &mut GameBoard, hex: usize, count: usize, key: &str) {
+    let nodes = board.adjacency.hex_nodes[hex];
+    for side in 0..count {
+        let road = road_between(board, nodes[side], nodes[(side + 1) % 6]);
+        board.roads[road].player_key = Some(String::from(key));
+    }
+}
+
+fn build_village(board: &mut GameBoard, node: usize, key: &str) {
+    board.nodes[node].player_key = Some(String::from(key));
+    board.nodes[node].building_type = BuildingType::Village;
+}
+
+#[test]
+fn straight_roads() {
+    let mut board = new_board();
+    assert_eq!(measure_longest_road(&board, "key1"), 0);
+
+    claim_sides(&mut board, 0, 4, "key1");
+    assert_eq!(measure_longest_road(&board, "key1"), 4);
+    assert_eq!(measure_longest_road(&board, "key2"), 0);
+}
+
+#[test]
+fn loops_count_every_road_once() {
+    let mut board = new_board();
+    claim_sides(&mut board, 0, 6, "key1");
+    assert_eq!(measure_longest_road(&board, "key1"), 6);
+
+    // A spur off the loop adds to it
+    let corner = board.adjacency.hex_nodes[0][0];
+    let spur = *board.adjacency.node_roads[corner].iter().find(|&&road| board.roads[road].player_key.is_none()).unwrap();
+    board.roads[spur].player_key = Some(String::from("key1"));
+    assert_eq!(measure_longest_road(&board, "key1"), 7);
+
+    // Two loops sharing a side can be run end to end
+    let mut board = new_board();
+    let neighbor = board.adjacency.hex_nodes[0].iter()
+        .flat_map(|&node| board.adjacency.node_hexes[node].clone())
+        .find(|&hex| hex != 0)
+        .unwrap();
+    claim_sides(&mut board, 0, 6, "key1");
+    claim_sides(&mut board, neighbor, 6, "key1");
+    assert_eq!(measure_longest_road(&board, "key1"), 11);
+}
+
+#[test]
+fn opponents_cut_roads() {
+    let mut board = new_board();
+    claim_sides(&mut board, 0, 4, "key1");
+    let middle = board.adjacency.hex_nodes[0][2];
+
+    build_village(&mut board, middle, "key1");
+    assert_eq!(measure_longest_road(&board, "key1"), 4);
+
+    build_village(&mut board, middle, "key2");
+    assert_eq!(measure_longest_road(&board, "key1"), 2);
+
+    // Closing the loop gives a way around the village
+    claim_sides(&mut board, 0, 6, "key1");
+    assert_eq!(measure_longest_road(&board, "key1"), 6);
+}
+
+#[test]
+fn longest_road_title() {
+    let mut board = new_board();
+    let mut players = Players::new();
+    players.add_player("key1", "name1").add_player("key2", "name2");
+
+    claim_sides(&mut board, 0, 3, "key1");
+    assert_eq!(find_longest_road(&board, &players, &None, 3), Some(String::from("key1")));
+    assert_eq!(find_longest_road(&board, &players, &None, 4), None);
+
+    // Ties stay with whoever had it first
+    let far = board.hexagons.len() - 1;
+    claim_sides(&mut board, far, 3, "key2");
+    assert_eq!(find_longest_road(&board, &players, &Some(String::from("key1")), 3), Some(String::from("key1")));
+
+    claim_sides(&mut board, far, 4, "key2");
+    assert_eq!(find_longest_road(&board, &players, &Some(String::from("key1")), 3), Some(String::from("key2")));
+}
+
+#[test]
+fn dense_networks() {
+    let mut board = new_board();
+    for road in board.roads.iter_mut() {
+        road.player_key = Some(String::from("key1"));
+    }
+
+    // Nobody can use every road, and a search through every way around would never finish
+    assert_eq!(measure_longest_road(&board, "key1"), 55);
+}
+
+// Tries every way along the roads from every node
+fn brute_force(board: &GameBoard, key: &str) -> u8 {
+    fn extend(board: &GameBoard, key: &str, node: usize, used: &mut Vec<usize>) -> usize {
+        let mut longest = used.len();
+        for &road in &board.adjacency.node_roads[node] {
+            if used.contains(&road) || board.roads[road].player_key.as_deref() != Some(key) { continue; }
+            let (a, b) = board.roads[road].inds;
+            let other = if a == node { b } else { a };
+            used.push(road);
+            let blocked = board.nodes[other].player_key.as_deref().is_some_and(|owner| owner != key);
+            longest = longest.max(if blocked { used.len() } else { extend(board, key, other, used) });
+            used.pop();
+        }
+        longest
+    }
+
+    (0..board.nodes.len()).map(|node| extend(board, key, node, &mut Vec::new())).max().unwrap_or(0) as u8
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn same_as_brute_force(
+        roads in prop::collection::vec((0..72usize, 0..3u8), 1..16),
+        villages in prop::collection::vec((0..54usize, 0..3u8), 0..6)
+    ) {
+        let mut board = new_board();
+        for (road, owner) in roads {
+            board.roads[road].player_key = Some(format!("key{}", owner));
+        }
+        for (node, owner) in villages {
+            build_village(&mut board, node, &format!("key{}", owner));
+        }
+
+        for key in ["key0", "key1", "key2"] {
+            prop_assert_eq!(measure_longest_road(&board, key), brute_force(&board, key));
+        }
+    }
+}
+
@@ -0,0 +1,47 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+use super::resources::Resource;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Card {
+    Bug,
+    VictoryPoint,
+    RoadBuilding,
+    YearOfPlenty,
+    Monopoly
+}
+
+/// Playing a card, along with whatever the card asks its player to choose
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Play {
+    Bug,
+    RoadBuilding,
+    YearOfPlenty(Resource, Resource),
+    Monopoly(Resource)
+}
+
+impl Play {
+    pub fn card(&self) -> Card {
+        match self {
+            Play::Bug => Card::Bug,
+            Play::RoadBuilding => Card::RoadBuilding,
+            Play::YearOfPlenty(..) => Card::YearOfPlenty,
+            Play::Monopoly(..) => Card::Monopoly
+        }
+    }
+}
+
+/// The canonical mix of 25 cards, shuffled
+pub fn new_deck<R: Rng>(rng: &mut R) -> Vec<Card> {
+    let mut deck = Vec::new();
+    deck.extend(vec![Card::Bug; 14]);
+    deck.extend(vec![Card::VictoryPoint; 5]);
+    deck.extend(vec![Card::RoadBuilding; 2]);
+    deck.extend(vec![Card::YearOfPlenty; 2]);
+    deck.extend(vec![Card::Monopoly; 2]);
+    deck.shuffle(rng);
+
+    deck
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use super::actions::Command;
+
+/// Something that changed the game, in the order it happened
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    Joined { key: String, name: String },
+    Acted { command: Command, roll_result: Option<(u8,u8)> }
+}
+
+/// Append-only record of everything needed to replay a game from its config
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionLog {
+    pub board_seed: Option<u64>,
+    pub events: Vec<Event>
+}
+
+impl ActionLog {
+    pub fn new() -> ActionLog {
+        ActionLog { board_seed: None, events: Vec::new() }
+    }
+
+    pub fn record(&mut self, event: Event) -> &mut ActionLog {
+        self.events.push(event);
+
+        self
+    }
+
+    /// Copies the log with every player's key swapped for whatever `rename` gives their seat
+    pub fn rename_players(&self, mut rename: impl FnMut(usize) -> String) -> ActionLog {
+        let mut names: HashMap<String, String> = HashMap::new();
+        let events = self.events.iter().map(|event| match event {
+            Event::Joined { key, name } => {
+                let seat = names.len();
+                let new_key = names.entry(key.clone()).or_insert_with(|| rename(seat));
+                Event::Joined { key: new_key.clone(), name: name.clone() }
+            },
+            Event::Acted { command, roll_result } => {
+                let mut command = command.clone();
+                // Anyone who never joined can't have acted, so there is nothing of theirs to keep
+                command.player = names.get(&command.player).cloned().unwrap_or_default();
+                Event::Acted { command, roll_result: *roll_result }
+            }
+        }).collect();
+
+        ActionLog { board_seed: self.board_seed, events }
+    }
+
+    pub fn reset(&mut self) -> &mut ActionLog {
+        self.board_seed = None;
+        self.events.truncate(0);
+
+        self
+    }
+}
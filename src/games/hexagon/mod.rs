@@ -1,16 +1,25 @@
 use std::collections::HashMap;
-use serde::{Serialize, Deserialize};
-use serde_json::to_string;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::Error;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_pcg::Pcg64;
 
 use crate::games::core::Phase;
 use crate::games::core::playe::Players;
 use crate::games::core::traits::Game;
+use crate::games::core::error::{ GameError, ErrorCode, ErrorContext };
 
 pub mod actions;
 mod board;
 mod colo;
 mod resources;
 mod bonuses;
+mod history;
+mod trading;
+mod cards;
+mod rules;
 
 use actions::{ 
     Actions, 
@@ -20,30 +29,232 @@ use actions::{
     roll_dice, 
     build_road,
     build_node,
+    upgrade_node,
     count_player_nodes,
+    score_player_nodes,
     count_player_roads
 };
-use board::GameBoard;
-use colo::get_player_color;
+use board::{ GameBoard, BoardState, BoardMap, NumberPlacement, BUILTIN_MAPS };
+pub use board::BoardLayout;
+use colo::{ get_player_color, PLAYER_COLORS };
 use resources::{ Resource, ResourceList };
-use bonuses::{ find_most_bugs, find_longest_road };
+use bonuses::{ find_most_bugs, find_longest_road, measure_longest_road };
+pub use history::{ ActionLog, Event };
+use trading::{ Offer, Offers };
+use cards::{ Card, Play, new_deck };
+pub use rules::Rules;
 
-#[derive(Serialize)]
+/// What everyone at the table gets to know about a player; `id` is their seat
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PublicPlayer {
+    id: usize,
+    name: String,
+    color: String,
+    cards: u32,
+    development_cards: usize,
+    idle: bool
+}
+
+/// Where a bonus on the scoreboard comes from
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum BonusSource {
+    MostBugs,
+    LongestRoad
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Bonus {
+    source: BonusSource,
+    points: u8
+}
+
+/// One player's line on the scoreboard; `hidden_points` is only filled in for the player it belongs to
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Score {
+    id: usize,
+    points: u8,
+    buildings: u8,
+    bonuses: Vec<Bonus>,
+    hidden_points: Option<u8>,
+    longest_road: u8,
+    bugs: u8
+}
+
+/// An open trade offer, with players shown by public ID
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PublicOffer {
+    id: usize,
+    from: Option<usize>,
+    to: Option<usize>,
+    give: ResourceList,
+    get: ResourceList,
+    declined: Vec<usize>
+}
+
+/// The game as one player is allowed to see it; other players only show up by public ID
+#[derive(Debug, PartialEq, Serialize)]
 pub struct Status {
-    key: String,
+    key: Option<String>,
+    id: Option<usize>,
     phase: Phase,
     round: u16,
-    colors: HashMap<String, String>,
-    resources: ResourceList
+    seed: Option<u64>,
+    active_player: Option<usize>,
+    roll_result: (u8,u8),
+    allowed_actions: Vec<Actions>,
+    // When the active player runs out of time, in milliseconds since the Unix epoch
+    turn_deadline: Option<u64>,
+    the_winner: Option<usize>,
+    players: Vec<PublicPlayer>,
+    scoreboard: Vec<Score>,
+    offers: Vec<PublicOffer>,
+    resources: ResourceList,
+    hand: Vec<Card>,
+    bugs: u8,
+    has_most_bugs: Option<usize>,
+    has_longest_road: Option<usize>,
+    rules: Rules,
+    board: BoardState
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     num_players: usize,
     score_to_win: u8,
-    game_board_width: u8
+    game_board_width: u8,
+    #[serde(default, deserialize_with = "deserialize_seed")]
+    seed: Option<u64>,
+    // Replaces the hexagon of `game_board_width` when there is one
+    #[serde(default, deserialize_with = "deserialize_map")]
+    map: Option<BoardMap>,
+    #[serde(default)]
+    number_placement: NumberPlacement,
+    #[serde(flatten)]
+    rules: Rules
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            num_players: 2,
+            score_to_win: 10,
+            game_board_width: 5,
+            seed: None,
+            map: None,
+            number_placement: NumberPlacement::Random,
+            rules: Rules::default()
+        }
+    }
+}
+
+// Widths past this make boards that take too long to lay out and nobody could finish
+const MAX_BOARD_WIDTH: u8 = 15;
+
+impl Config {
+    /// Rejects games that could never be set up or played to the end
+    pub fn validate(&self) -> Result<(), GameError> {
+        let invalid = |message: String| Err(GameError::new(ErrorCode::InvalidConfig, &message));
+
+        if self.num_players == 0 || self.num_players > PLAYER_COLORS.len() {
+            return invalid(format!("Games need between 1 and {} players.", PLAYER_COLORS.len()));
+        }
+        if self.score_to_win == 0 { return invalid(String::from("The score to win has to be at least one.")); }
+
+        let num_hexagons = match &self.map {
+            Some(map) => {
+                map.validate()?;
+                map.tiles.len()
+            },
+            None => {
+                if self.game_board_width.is_multiple_of(2) || !(3..=MAX_BOARD_WIDTH).contains(&self.game_board_width) {
+                    return invalid(format!("The board width has to be an odd number from 3 to {}.", MAX_BOARD_WIDTH));
+                }
+                let width = usize::from(self.game_board_width);
+                (3 * width * width + 1) / 4
+            }
+        };
+
+        // Leave every player room for their first two villages
+        if num_hexagons < 2 * self.num_players {
+            return invalid(format!("This board only has room for {} players.", num_hexagons / 2));
+        }
+
+        // Some maps leave no way to keep the 6s and 8s apart, which only laying one out can tell
+        if self.number_placement == NumberPlacement::Balanced {
+            let mut rng = GameRng::seed_from_u64(self.seed.unwrap_or_else(|| thread_rng().gen()));
+            GameBoard::new().setup(&self.get_map(), self.number_placement, &mut rng)?;
+        }
+
+        self.rules.validate()
+    }
+
+    /// The map to lay the board out from
+    pub fn get_map(&self) -> BoardMap {
+        self.map.clone().unwrap_or_else(|| BoardMap::hexagon(self.game_board_width))
+    }
+
+    /// Balanced boards settle on a seed up front, so the board `validate` lays out is the one
+    /// the game goes on to use
+    pub fn with_seed(self) -> Config {
+        if self.number_placement != NumberPlacement::Balanced || self.seed.is_some() { return self; }
+        Config { seed: Some(thread_rng().gen()), ..self }
+    }
+
+    /// The same config, leaving it to the game to pick a seed
+    pub fn without_seed(self) -> Config {
+        Config { seed: None, ..self }
+    }
+}
+
+// Forms send a blank seed as an empty string, which should mean "pick one for me"
+fn deserialize_seed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seed {
+        Number(u64),
+        Text(String)
+    }
+
+    match Option::<Seed>::deserialize(deserializer)? {
+        Some(Seed::Number(seed)) => Ok(Some(seed)),
+        Some(Seed::Text(text)) if text.trim().is_empty() => Ok(None),
+        Some(Seed::Text(text)) => text.trim().parse().map(Some).map_err(D::Error::custom),
+        None => Ok(None)
+    }
 }
 
+// Maps can be picked by name from a form, or spelled out in full
+fn deserialize_map<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<BoardMap>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Map {
+        Name(String),
+        Definition(BoardMap)
+    }
+
+    match Option::<Map>::deserialize(deserializer)? {
+        Some(Map::Name(name)) if name.trim().is_empty() => Ok(None),
+        Some(Map::Name(name)) => BoardMap::builtin(name.trim())
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("unknown map {:?}, expected one of {:?}", name, BUILTIN_MAPS))),
+        Some(Map::Definition(map)) => Ok(Some(map)),
+        None => Ok(None)
+    }
+}
+
+/// Every random decision in a game is drawn from this, so a seed reproduces the whole game
+pub type GameRng = Pcg64;
+
+// Snapshots from before the game kept its RNG carry on with an unpredictable one
+fn fresh_rng() -> GameRng {
+    GameRng::seed_from_u64(thread_rng().gen())
+}
+
+/// When the active player's turn started
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TurnClock(Option<SystemTime>);
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct HexagonIsland {
     phase: Phase,
@@ -58,7 +269,24 @@ pub struct HexagonIsland {
     has_most_bugs: Option<String>,
     has_longest_road: Option<String>,
     board: GameBoard,
-    the_winner: Option<String>
+    the_winner: Option<String>,
+    #[serde(default)]
+    offers: Offers,
+    #[serde(default)]
+    deck: Vec<Card>,
+    #[serde(default)]
+    hands: HashMap<String, Vec<Card>>,
+    #[serde(default)]
+    card_played: bool,
+    #[serde(default)]
+    log: ActionLog,
+    #[serde(default)]
+    turn_started: TurnClock,
+    // How many turns in a row each player has run out of time on
+    #[serde(default)]
+    idle_turns: HashMap<String, u8>,
+    #[serde(default = "fresh_rng")]
+    rng: GameRng
 }
 
 impl Game for HexagonIsland {
@@ -72,11 +300,7 @@ impl Game for HexagonIsland {
             round: 0,
             players: Players::new(),
             last_action: Actions::None,
-            config: Config {
-                num_players: 2,
-                score_to_win: 10,
-                game_board_width: 5
-            },
+            config: Config::default(),
             roll_result: (0,0),
             player_colors: HashMap::new(),
             player_resources: HashMap::new(),
@@ -84,7 +308,15 @@ impl Game for HexagonIsland {
             has_most_bugs: None,
             has_longest_road: None,
             board: GameBoard::new(),
-            the_winner: None
+            the_winner: None,
+            offers: Offers::new(),
+            deck: Vec::new(),
+            hands: HashMap::new(),
+            card_played: false,
+            log: ActionLog::new(),
+            turn_started: TurnClock::default(),
+            idle_turns: HashMap::new(),
+            rng: GameRng::seed_from_u64(0)
         }
     }
 
@@ -112,47 +344,72 @@ impl Game for HexagonIsland {
         self.player_colors.clear();
         self.bugs.clear();
         self.has_most_bugs = None;
+        self.has_longest_road = None;
         self.roll_result = (0,0);
         self.the_winner = None;
         self.last_action = Actions::None;
+        self.offers.clear();
+        self.deck.clear();
+        self.hands.clear();
+        self.card_played = false;
+        self.log.reset();
+        self.turn_started = TurnClock::default();
+        self.idle_turns.clear();
 
         self
     }
 
-    fn add_player(&mut self, key: &str, name: &str) -> Result<&mut HexagonIsland, &'static str> {
+    fn add_player(&mut self, key: &str, name: &str) -> Result<&mut HexagonIsland, GameError> {
 
         if self.players.cardinality == self.config.num_players {
-            return Err("Cannot add player; exceeds maximum number of players.");
+            return Err(GameError::new(ErrorCode::GameFull, "Cannot add player; exceeds maximum number of players."));
         }
 
-        println!("Added player");
+        tracing::debug!("added player {}", name);
+
+        // The last player to sit down gets the board laid out, so make sure that works before seating them
+        if self.players.cardinality + 1 == self.config.num_players {
+            let seed = self.config.seed.unwrap_or_else(|| thread_rng().gen());
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut board = GameBoard::new();
+            board.setup(&self.config.get_map(), self.config.number_placement, &mut rng)?;
+            board.price_generic_harbors(self.config.rules.bank_ratio);
+            // Keep the seed so the same game can be played out again on replay
+            self.config.seed = Some(seed);
+            self.log.board_seed = Some(seed);
+            self.board = board;
+            self.deck = new_deck(&mut rng);
+            self.rng = rng;
+        }
 
         self.player_colors.insert(String::from(key), get_player_color(self.players.cardinality));
         self.player_resources.insert(String::from(key), ResourceList::new());
         self.bugs.insert(String::from(key), 0);
+        self.hands.insert(String::from(key), Vec::new());
         self.players.add_player(key, name);
+        self.log.record(Event::Joined { key: String::from(key), name: String::from(name) });
 
         if self.players.cardinality == self.config.num_players { 
             self.next_phase();
-            self.board.setup(self.config.game_board_width);
+            self.start_turn_clock(SystemTime::now());
         }
 
         Ok(self)
     }
 
-    fn set_active_player(&mut self, key: &str) -> Result<&mut HexagonIsland, &'static str> {
+    fn set_active_player(&mut self, key: &str) -> Result<&mut HexagonIsland, GameError> {
         match self.players.set_active_player(key) {
             Ok(_) => Ok(self),
             Err(e) => Err(e)
         }
     }
     
-    fn next_player(&mut self) -> Result<&mut HexagonIsland, &'static str> {
+    fn next_player(&mut self) -> Result<&mut HexagonIsland, GameError> {
         match self.players.next_player(1) {
             Ok(_) => {
                 let active_player = self.players.active_player
                     .as_ref()
-                    .ok_or("Can't get active player")?;
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get active player"))?;
                 let active_player_index = self.players.list.iter().position(|p| p == active_player);
                 if active_player_index == Some(0) { self.next_round(); }
                 Ok(self)
@@ -161,15 +418,14 @@ impl Game for HexagonIsland {
         }
     }
 
-    fn previous_player(&mut self) -> Result<&mut HexagonIsland, &'static str> {
+    fn previous_player(&mut self) -> Result<&mut HexagonIsland, GameError> {
         match self.players.next_player(-1) {
             Ok(_) => Ok(self),
             Err(e) => Err(e)
         }
     }
 
-    // TODO: Can I just use serde to serialize this?
-    fn get_game_status(&self, key: &str) -> String {
+    fn get_game_status(&self, key: &str) -> Status {
         let mut allowed_actions = Vec::<Actions>::new();
         if let Some(active_player) = &self.players.active_player {
             if active_player.key == key {
@@ -185,99 +441,351 @@ impl Game for HexagonIsland {
                 allowed_actions = vec![Actions::None];
             }
         }
-        let resources: ResourceList;
-        match self.player_resources.get(key) {
-            Some(list) => resources = *list,
-            None => resources = ResourceList::new()
-        }
-        let bugs: u8;
-        match self.bugs.get(key) {
-            Some(bug) => bugs = *bug,
-            None => bugs = 0
-        }
-        let status = String::new() + 
-            "{" +
-                "\"key\": " + "\"" + key + "\"," +
-                "\"phase\": " + "\"" + &self.phase.to_string() + "\"," +
-                "\"round\": " + &self.round.to_string() + "," +
-                "\"active_player\": " + &to_string(&self.players.active_player).unwrap() + "," +
-                "\"roll_result\": " + &to_string(&self.roll_result).unwrap() + "," +
-                "\"allowed_actions\": " + &to_string(&allowed_actions).unwrap() + "," +
-                "\"the_winner\": " + &to_string(&self.the_winner).unwrap() + "," +
-                "\"colors\": " + &to_string(&self.player_colors).unwrap() + "," +
-                "\"resources\": " + &to_string(&resources).unwrap() + "," +
-                "\"bugs\": " + &to_string(&bugs).unwrap() + "," +
-                "\"has_most_bugs\": " + &to_string(&self.has_most_bugs).unwrap() + "," +
-                "\"has_longest_road\": " + &to_string(&self.has_longest_road).unwrap() + "," +
-                "\"board\": " + &to_string(&self.board).unwrap() +
-            "}";
-
-        status
-    }
-
-    fn configure_game(&mut self, config: Self::Config) -> Result<&mut Self, &'static str> {
+        // Anyone with an offer to answer can do so, whether or not it is their turn
+        if self.check_trading_open().is_ok() && self.offers.list.iter().any(|o| o.can_answer(key)) {
+            allowed_actions.retain(|a| *a != Actions::None);
+            allowed_actions.extend([Actions::AcceptTrade, Actions::RejectTrade, Actions::CounterTrade]);
+        }
+        let resources = match self.player_resources.get(key) {
+            Some(list) => *list,
+            None => ResourceList::new()
+        };
+        let bugs = match self.bugs.get(key) {
+            Some(bug) => *bug,
+            None => 0
+        };
+
+        let players = self.players.list.iter().enumerate().map(
+            | (id, player) | PublicPlayer {
+                id,
+                name: player.name.clone(),
+                color: self.player_colors.get(&player.key).cloned().unwrap_or_default(),
+                cards: self.player_resources.get(&player.key).map_or(0, |list| list.count()),
+                development_cards: self.hands.get(&player.key).map_or(0, |hand| hand.len()),
+                idle: self.is_idle(&player.key)
+            }
+        ).collect();
+
+        let scoreboard = self.players.list.iter().enumerate().map(
+            | (id, player) | {
+                let mut score = self.score_player(id, &player.key);
+                if player.key != key { score.hidden_points = None; }
+                score
+            }
+        ).collect();
+
+        let offers = self.offers.list.iter().map(
+            | offer | PublicOffer {
+                id: offer.id,
+                from: self.public_id(&offer.from),
+                to: offer.to.as_deref().and_then(|k| self.public_id(k)),
+                give: offer.bundle.give,
+                get: offer.bundle.get,
+                declined: offer.declined.iter().filter_map(|k| self.public_id(k)).collect()
+            }
+        ).collect();
+
+        let id = self.public_id(key);
+        let public_id = | owner: &Option<String> | owner.as_deref().and_then(|k| self.public_id(k));
+
+        Status {
+            key: id.map(|_| String::from(key)),
+            id,
+            phase: self.phase.clone(),
+            round: self.round,
+            // Knowing the seed means knowing every roll and card to come, so it waits for the end
+            seed: if self.phase == Phase::End { self.config.seed } else { None },
+            active_player: self.players.active_player.as_ref().and_then(|p| self.public_id(&p.key)),
+            roll_result: self.roll_result,
+            allowed_actions,
+            turn_deadline: self.turn_deadline()
+                .and_then(|deadline| deadline.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as u64),
+            the_winner: public_id(&self.the_winner),
+            players,
+            scoreboard,
+            offers,
+            resources,
+            hand: self.hands.get(key).cloned().unwrap_or_default(),
+            bugs,
+            has_most_bugs: public_id(&self.has_most_bugs),
+            has_longest_road: public_id(&self.has_longest_road),
+            rules: self.config.rules.clone(),
+            board: self.board.get_state(|k| self.public_id(k))
+        }
+    }
+
+    fn configure_game(&mut self, config: Self::Config) -> Result<&mut Self, GameError> {
         match self.phase {
             Phase::Boot => {
+                let config = config.with_seed();
+                config.validate()?;
                 self.config = config;
                 Ok(self)
             },
-            _ => Err("Cannot configure game outside of boot phase!")
+            _ => Err(GameError::new(ErrorCode::WrongPhase, "Cannot configure game outside of boot phase!"))
         }
     }
 
     fn find_the_winner(&mut self) -> &mut HexagonIsland {
-        for player in self.players.list.iter() {
-            let building_score = count_player_nodes(&player.key, &self.board.nodes);
-            
-            let mut most_bugs_bonus = 0;
-            match &self.has_most_bugs {
-                Some(has_most_bugs) => {
-                    if player.key == *has_most_bugs { most_bugs_bonus = 2; }
-                },
-                None => {}
+        for (id, player) in self.players.list.iter().enumerate() {
+            let score = self.score_player(id, &player.key);
+            if score.points + score.hidden_points.unwrap_or(0) >= self.config.score_to_win {
+                self.the_winner = Some(player.key.clone());
             }
+        }
+        self
+    }
 
-            let mut longest_road_bonus = 0;
-            match &self.has_longest_road {
-                Some(has_longest_road) => {
-                    if player.key == *has_longest_road { longest_road_bonus = 2; }
-                },
-                None => {}
+    fn process_action(&mut self, command: Self::Command) -> Result<&mut HexagonIsland, GameError> {
+        let player = command.player.clone();
+        self.act(command)?;
+        // Doing anything at all shows they are still at the table
+        self.idle_turns.remove(&player);
+
+        Ok(self)
+    }
+}
+
+impl HexagonIsland {
+    pub fn get_phase(&self) -> &Phase {
+        &self.phase
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_log(&self) -> &ActionLog {
+        &self.log
+    }
+
+    pub fn get_layout(&self) -> BoardLayout {
+        self.board.get_layout()
+    }
+
+    // Keys are secret, so everyone else knows a player by their seat at the table
+    fn public_id(&self, key: &str) -> Option<usize> {
+        self.players.list.iter().position(|p| p.key == key)
+    }
+
+    // Everything a player has scored, hidden cards included
+    fn score_player(&self, id: usize, key: &str) -> Score {
+        let buildings = score_player_nodes(key, &self.board.nodes);
+
+        let mut bonuses = Vec::new();
+        if self.has_most_bugs.as_deref() == Some(key) {
+            bonuses.push(Bonus { source: BonusSource::MostBugs, points: self.config.rules.most_bugs_bonus });
+        }
+        if self.has_longest_road.as_deref() == Some(key) {
+            bonuses.push(Bonus { source: BonusSource::LongestRoad, points: self.config.rules.longest_road_bonus });
+        }
+
+        let victory_points = self.hands.get(key).map_or(0, |hand| {
+            hand.iter().filter(|c| **c == Card::VictoryPoint).count() as u8
+        });
+
+        Score {
+            id,
+            points: bonuses.iter().fold(buildings, |acc, bonus| acc + bonus.points),
+            buildings,
+            bonuses,
+            hidden_points: Some(victory_points),
+            longest_road: measure_longest_road(&self.board, key),
+            bugs: self.bugs.get(key).copied().unwrap_or(0)
+        }
+    }
+
+    /// Gives the active player a full turn from `now`
+    pub fn start_turn_clock(&mut self, now: SystemTime) {
+        self.turn_started = TurnClock(Some(now));
+    }
+
+    // Turns are only timed while there is someone whose turn it is
+    fn turn_deadline(&self) -> Option<SystemTime> {
+        let limit = self.config.rules.turn_time_limit;
+        if limit == 0 || !matches!(self.phase, Phase::Setup | Phase::Play) { return None; }
+
+        Some(self.turn_started.0? + Duration::from_secs(limit.into()))
+    }
+
+    fn is_idle(&self, key: &str) -> bool {
+        self.idle_turns.get(key).is_some_and(|&turns| turns >= self.config.rules.idle_turn_limit)
+    }
+
+    /// Once the active player runs out of time, places their village and road during setup,
+    /// otherwise rolls for them if they haven't yet and moves the scorpion anywhere it can go
+    /// if it has to move, then ends their turn
+    pub fn expire_turn(&mut self, now: SystemTime) -> Result<bool, GameError> {
+        if self.turn_deadline().is_none_or(|deadline| deadline > now) { return Ok(false); }
+        let key = self.players.active_player
+            .as_ref()
+            .map(|p| p.key.clone())
+            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get active player"))?;
+
+        let allowed = | game: &HexagonIsland | {
+            next_allowed_actions(&game.last_action, game.roll_result.0 + game.roll_result.1)
+        };
+        if self.phase == Phase::Setup {
+            if self.last_action != Actions::PlaceVillageAndRoad {
+                let command = self.random_setup_placement(&key)?;
+                self.act(command)?;
             }
+        } else if allowed(self).contains(&Actions::RollDice) {
+            self.act(Command::new(Actions::RollDice, key.clone()))?;
+        }
+        if self.phase == Phase::Play && allowed(self).contains(&Actions::MoveScorpion) {
+            let command = self.random_scorpion_move(&key)?;
+            self.act(command)?;
+        }
+        self.act(Command::new(Actions::EndTurn, key.clone()))?;
+        self.start_turn_clock(now);
+        *self.idle_turns.entry(key).or_insert(0) += 1;
 
-            let score = building_score + most_bugs_bonus + longest_road_bonus;
-            if score >= self.config.score_to_win {
-                self.the_winner = Some(player.key.clone());
+        Ok(true)
+    }
+
+    // Any free node that respects the two-space rule, along with a free road leading off it
+    fn random_setup_placement(&self, key: &str) -> Result<Command, GameError> {
+        let board = &self.board;
+        let is_free = | n: usize | board.nodes[n].player_key.is_none()
+            && board.adjacency.node_nodes[n].iter().all(|&m| board.nodes[m].player_key.is_none());
+        let spots: Vec<(usize,usize)> = (0..board.nodes.len())
+            .filter(|&n| is_free(n))
+            .flat_map(|n| board.adjacency.node_roads[n].iter()
+                .filter(|&&r| board.roads[r].player_key.is_none())
+                .map(move |&r| (n, r)))
+            .collect();
+        let &(node, road) = spots.choose(&mut thread_rng())
+            .ok_or_else(|| GameError::new(ErrorCode::InvalidTarget, "There is nowhere left to build."))?;
+
+        let mut command = Command::new(Actions::PlaceVillageAndRoad, String::from(key));
+        command.target[0] = Some( (Target::Node, node) );
+        command.target[1] = Some( (Target::Road, road) );
+
+        Ok(command)
+    }
+
+    // The choice ends up in the log, so it doesn't come out of the game's RNG and replays stay in step
+    fn random_scorpion_move(&self, key: &str) -> Result<Command, GameError> {
+        let mut rng = thread_rng();
+        let hexes: Vec<usize> = (0..self.board.hexagons.len())
+            .filter(|&hex| Some(hex) != self.board.scorpion_index)
+            .collect();
+        let &hex = hexes.choose(&mut rng)
+            .ok_or_else(|| GameError::new(ErrorCode::InvalidTarget, "There is nowhere for the scorpion to go."))?;
+        let victims: Vec<usize> = self.board.find_neighboring_nodes(hex).iter()
+            .filter_map(|&n| self.board.nodes[n].player_key.as_deref())
+            .filter(|&k| k != key)
+            .filter_map(|k| self.public_id(k))
+            .collect();
+
+        let mut command = Command::new(Actions::MoveScorpion, String::from(key));
+        command.target[0] = Some( (Target::Hex, hex) );
+        if let Some(&victim) = victims.choose(&mut rng) {
+            command.target[1] = Some( (Target::Player, victim) );
+        }
+
+        Ok(command)
+    }
+
+    /// Rebuilds a game by feeding a recorded log back through a freshly configured one
+    pub fn replay(mut config: Config, log: &ActionLog) -> Result<HexagonIsland, GameError> {
+        if config.seed.is_none() { config.seed = log.board_seed; }
+        let mut game = HexagonIsland::new();
+        game.configure_game(config)?;
+
+        for event in log.events.iter() {
+            match event {
+                Event::Joined { key, name } => { game.add_player(key, name)?; },
+                Event::Acted { command, roll_result } => {
+                    game.act(command.clone())?;
+                    // With the same seed the dice should come up the same way they did before
+                    if roll_result.is_some() && *roll_result != Some(game.roll_result) {
+                        return Err(GameError::new(ErrorCode::ReplayMismatch, "Replay does not match the recorded dice."));
+                    }
+                }
             }
         }
-        self
+
+        Ok(game)
     }
 
-    fn process_action(&mut self, command: Self::Command) -> Result<&mut HexagonIsland, &'static str> {
-        
+    /// Whether two games match apart from when the current turn started, which a replay
+    /// or a restart can't reproduce
+    pub fn eq_ignoring_clock(&self, other: &HexagonIsland) -> bool {
+        let HexagonIsland {
+            phase, round, players, last_action, config, roll_result, player_colors,
+            player_resources, bugs, has_most_bugs, has_longest_road, board, the_winner,
+            offers, deck, hands, card_played, log, turn_started: _, idle_turns, rng
+        } = self;
+
+        *phase == other.phase && *round == other.round && *players == other.players
+            && *last_action == other.last_action && *config == other.config
+            && *roll_result == other.roll_result && *player_colors == other.player_colors
+            && *player_resources == other.player_resources && *bugs == other.bugs
+            && *has_most_bugs == other.has_most_bugs && *has_longest_road == other.has_longest_road
+            && *board == other.board && *the_winner == other.the_winner && *offers == other.offers
+            && *deck == other.deck && *hands == other.hands && *card_played == other.card_played
+            && *log == other.log && *idle_turns == other.idle_turns && *rng == other.rng
+    }
+
+    // Applies a command and, if it was accepted, appends it to the log.
+    fn act(&mut self, command: Command) -> Result<&mut HexagonIsland, GameError> {
+        let recorded = command.clone();
+        let active_player = self.players.active_player.as_ref().map(|p| p.key.clone());
+        self.resolve_action(command)?;
+
+        // Every new turn gets the full time, including a second one in a row during setup
+        if recorded.action == Actions::EndTurn
+            || self.players.active_player.as_ref().map(|p| &p.key) != active_player.as_ref() {
+            self.start_turn_clock(SystemTime::now());
+        }
+
+        let roll_result = match recorded.action {
+            Actions::RollDice => Some(self.roll_result),
+            _ => None
+        };
+        self.log.record(Event::Acted { command: recorded, roll_result });
+
+        Ok(self)
+    }
+
+    fn resolve_action(&mut self, command: Command) -> Result<&mut HexagonIsland, GameError> {
+
+        // Answering a trade offer is the one thing players can do out of turn
+        if let Actions::AcceptTrade | Actions::RejectTrade | Actions::CounterTrade = command.action {
+            return self.negotiate(command);
+        }
+
         let active_player = self.players.active_player
             .as_ref()
-            .ok_or("Can't get active player")?;
-        if command.player != active_player.key { return Err("It is not your turn."); }
+            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get active player"))?;
+        if command.player != active_player.key { return Err(GameError::new(ErrorCode::NotYourTurn, "It is not your turn.")); }
         
-        println!("{:?}", command);
+        tracing::debug!("{:?}", command);
 
         match self.phase {
             Phase::Setup => match command.action {
                 Actions::PlaceVillageAndRoad => {
-                    println!("{:?}", self.last_action);
+                    tracing::debug!("{:?}", self.last_action);
                     if self.last_action != Actions::None && self.last_action != Actions::EndTurn {
-                        return Err("That is not an allowed action right now.");
+                        return Err(GameError::new(ErrorCode::ActionNotAllowed, "That is not an allowed action right now."));
                     }
                     let (num_nodes, node_index) = command.get_first(Target::Node);
                     let (num_roads, road_index) = command.get_first(Target::Road);
                     if num_nodes != 1 || num_roads != 1 {
-                        return Err("Must select one node and one road during setup.");
+                        return Err(GameError::new(ErrorCode::InvalidTarget, "Must select one node and one road during setup."));
                     }
 
-                    let adj_nodes = self.board.roads[road_index].inds;
+                    let adj_nodes = self.board.roads.get(road_index)
+                        .ok_or_else(|| {
+                            GameError::new(ErrorCode::InvalidIndex, "Selected road is not on the board.")
+                                .with_context(ErrorContext::Road(road_index))
+                        })?
+                        .inds;
                     if adj_nodes.0 != node_index && adj_nodes.1 != node_index {
-                        return Err("Selected node and road must be next to each other.");
+                        return Err(GameError::new(ErrorCode::InvalidTarget, "Selected node and road must be next to each other."));
                     }
 
                     build_node(
@@ -285,6 +793,7 @@ impl Game for HexagonIsland {
                         command.player.clone(),
                         &mut self.board.nodes,
                         &self.board.roads,
+                        &self.board.adjacency,
                         true
                     )?;
                     build_road(
@@ -292,6 +801,7 @@ impl Game for HexagonIsland {
                         command.player.clone(),
                         &self.board.nodes,
                         &mut self.board.roads,
+                        &self.board.adjacency,
                         true
                     )?;
 
@@ -300,9 +810,9 @@ impl Game for HexagonIsland {
                     Ok(self)
                 },
                 Actions::EndTurn => {
-                    println!("{:?}", self.last_action);
+                    tracing::debug!("{:?}", self.last_action);
                     if self.last_action != Actions::PlaceVillageAndRoad {
-                        return Err("That is not an allowed action right now.");
+                        return Err(GameError::new(ErrorCode::ActionNotAllowed, "That is not an allowed action right now."));
                     }
                     // TODO: Refactor into a function in board
                     let (
@@ -329,7 +839,7 @@ impl Game for HexagonIsland {
                         for (player_key,resource) in spoils {
                             let resources = self.player_resources
                                 .get_mut(&player_key)
-                                .ok_or("Can't get player resources.")?;
+                                .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
                             if resource != Resource::Desert { resources.deposit([resource])?; }
                         }
                         self.next_phase();
@@ -342,29 +852,29 @@ impl Game for HexagonIsland {
 
                     Ok(self)
                 },
-                _ => Err("That is not an allowed action during the Setup Phase.")
+                _ => Err(GameError::new(ErrorCode::WrongPhase, "That is not an allowed action during the Setup Phase."))
             }, 
             Phase::Play => {
                 // Check if command.action is allowed
                 let roll_sum = self.roll_result.0 + self.roll_result.1;
                 let allowed_actions = next_allowed_actions(&self.last_action, roll_sum);
-                let valid_action = allowed_actions.iter().any(|&a| a == command.action);
+                let valid_action = allowed_actions.contains(&command.action);
                 if !valid_action {
-                    return Err("That is not an allowed action right now.");
+                    return Err(GameError::new(ErrorCode::ActionNotAllowed, "That is not an allowed action right now."));
                 }
 
                 match command.action {
                     Actions::RollDice => {
-                        self.roll_result = roll_dice();
+                        self.roll_result = roll_dice(&mut self.rng);
                         let roll_sum = self.roll_result.0 + self.roll_result.1;
                         match roll_sum {
-                            7 => (), // Move the scorpion
+                            7 => self.discard_half(), // Then move the scorpion
                             _ => {
                                 let spoils = self.board.resolve_roll(roll_sum);
                                 for (player_key, resource) in spoils {
                                     let resources = self.player_resources
                                         .get_mut(&player_key)
-                                        .ok_or("Can't get player resources.")?;
+                                        .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
                                     resources.deposit([resource])?;
                                 }
                             }
@@ -375,90 +885,106 @@ impl Game for HexagonIsland {
                     Actions::BuildStuff => {
                         let resources = self.player_resources
                             .get_mut(&command.player)
-                            .ok_or("Can't get player resources.")?;
+                            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
+
+                        let rules = &self.config.rules;
 
                         let roads = command.get_all(Target::Road);
                         for road in roads {
-                            resources.check([Resource::Block, Resource::Timber])?;
+                            resources.covers(rules.road_cost)?;
                             build_road(
                                 road, 
                                 command.player.clone(), 
                                 &self.board.nodes,
                                 &mut self.board.roads,
+                                &self.board.adjacency,
                                 false
                             )?;
-                            resources.deduct([Resource::Block, Resource::Timber])?;
+                            resources.withdraw(rules.road_cost)?;
                         }
 
                         let nodes = command.get_all(Target::Node);
                         for node in nodes {
-                            resources.check([Resource::Block, Resource::Timber, Resource::Fiber, Resource::Cereal])?;
+                            resources.covers(rules.village_cost)?;
                             build_node(
                                 node,
                                 command.player.clone(),
                                 &mut self.board.nodes,
                                 &self.board.roads,
+                                &self.board.adjacency,
                                 false
                             )?;
-                            resources.deduct([Resource::Block, Resource::Timber, Resource::Fiber, Resource::Cereal])?;
+                            resources.withdraw(rules.village_cost)?;
+                        }
+
+                        let towns = command.get_all(Target::Town);
+                        for town in towns {
+                            resources.covers(rules.town_cost)?;
+                            upgrade_node(town, &command.player, &mut self.board.nodes)?;
+                            resources.withdraw(rules.town_cost)?;
                         }
 
-                        self.has_longest_road = find_longest_road(&self.board.roads, &self.players, &self.has_longest_road);
+                        self.has_longest_road = find_longest_road(&self.board, &self.players, &self.has_longest_road, self.config.rules.longest_road_threshold);
                         self.find_the_winner();
                         
                         self.last_action = command.action;
                         Ok(self)
                     },
                     Actions::MoveScorpion => {
-                        let (num_hex, hex_index) = command.get_first(Target::Hex);
-                        
-                        if num_hex != 1 {
-                            return Err("Must select one hexagon when moving the scorpion.");
-                        }
-
-                        if hex_index >= self.board.hexagons.len() {
-                            return Err("Cannot move scorpion; invalid hexagon index.");
-                        }
-
-                        self.board.scorpion_index = Some(hex_index);
+                        self.move_scorpion(&command)?;
 
                         self.last_action = command.action;
                         Ok(self)
                     },
                     Actions::Trade => {
+                        let trades = command.get_trade()?;
+                        let ratio = self.board.trade_ratio(&command.player, trades.0, self.config.rules.bank_ratio);
+
                         let resources = self.player_resources
                             .get_mut(&command.player)
-                            .ok_or("Can't get player resources.")?;
-
-                        let trades = command.get_trade()?;
+                            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
 
-                        resources.trade(trades.0, trades.1)?;
+                        resources.trade(trades.0, trades.1, ratio)?;
 
                         self.last_action = command.action;
                         Ok(self)
                     },
+                    Actions::OfferTrade => {
+                        let bundle = command.get_offer()?;
+                        self.check_offer(&command.player, &bundle)?;
+                        self.offers.propose(&command.player, None, bundle);
+
+                        Ok(self)
+                    },
                     Actions::BuyBug => {
                         let resources = self.player_resources
                             .get_mut(&command.player)
-                            .ok_or("Can't get player resources.")?;
+                            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
 
-                        resources.check([Resource::Rock, Resource::Fiber, Resource::Cereal])?;
-                        
-                        let bugs = self.bugs
-                            .get_mut(&command.player)
-                            .ok_or("Can't get player bugs")?;
+                        resources.covers(self.config.rules.card_cost)?;
 
-                        *bugs = *bugs + 1;
+                        let card = self.deck.pop()
+                            .ok_or_else(|| GameError::new(ErrorCode::EmptyDeck, "There are no cards left to buy."))?;
 
-                        resources.deduct([Resource::Rock, Resource::Fiber, Resource::Cereal])?;
+                        resources.withdraw(self.config.rules.card_cost)?;
+                        self.hands.entry(command.player.clone()).or_default().push(card);
 
-                        self.has_most_bugs = find_most_bugs(&self.bugs, &self.has_most_bugs);
+                        // Victory point cards count as soon as they are drawn
                         self.find_the_winner();
 
                         self.last_action = command.action;
                         Ok(self)
                     },
+                    Actions::PlayCard => {
+                        self.play_card(&command)?;
+
+                        self.last_action = command.action;
+                        Ok(self)
+                    },
                     Actions::EndTurn => {
+                        // Offers don't outlast the turn they were made in
+                        self.offers.clear();
+                        self.card_played = false;
                         match &self.the_winner {
                             Some(_) => { self.next_phase(); }
                             None => { self.next_player()?; }
@@ -468,11 +994,227 @@ impl Game for HexagonIsland {
                         Ok(self)
                     },
                     Actions::None => Ok(self),
-                    _ => Err("That action is not supported during the Play phase.")
+                    _ => Err(GameError::new(ErrorCode::WrongPhase, "That action is not supported during the Play phase."))
                 }
             },
-            _ => Err("Can only take action during the Setup or Play phases.")
+            _ => Err(GameError::new(ErrorCode::WrongPhase, "Can only take action during the Setup or Play phases."))
+        }
+    }
+
+    fn move_scorpion(&mut self, command: &Command) -> Result<(), GameError> {
+        let (num_hex, hex_index) = command.get_first(Target::Hex);
+        
+        if num_hex != 1 {
+            return Err(GameError::new(ErrorCode::InvalidTarget, "Must select one hexagon when moving the scorpion."));
+        }
+
+        if hex_index >= self.board.hexagons.len() {
+            return Err(
+                GameError::new(ErrorCode::InvalidIndex, "Cannot move scorpion; invalid hexagon index.")
+                    .with_context(ErrorContext::Hex(hex_index))
+            );
         }
+
+        if self.board.scorpion_index == Some(hex_index) {
+            return Err(
+                GameError::new(ErrorCode::InvalidTarget, "The scorpion has to move to a different hexagon.")
+                    .with_context(ErrorContext::Hex(hex_index))
+            );
+        }
+
+        // Anyone else with a building on the new hexagon can be stolen from
+        let victims: Vec<usize> = self.board.find_neighboring_nodes(hex_index).iter()
+            .filter_map(|&n| self.board.nodes[n].player_key.as_deref())
+            .filter(|&k| k != command.player)
+            .filter_map(|k| self.public_id(k))
+            .collect();
+        let (num_players, victim) = command.get_first(Target::Player);
+        if !victims.is_empty() && (num_players != 1 || !victims.contains(&victim)) {
+            return Err(GameError::new(ErrorCode::InvalidTarget, "Must select one player next to the scorpion to steal from."));
+        }
+        if victims.is_empty() && num_players != 0 {
+            return Err(GameError::new(ErrorCode::InvalidTarget, "There is nobody next to the scorpion to steal from."));
+        }
+
+        self.board.scorpion_index = Some(hex_index);
+
+        if !victims.is_empty() {
+            let victim_key = self.players.list[victim].key.clone();
+            let stolen = self.player_resources
+                .get_mut(&victim_key)
+                .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?
+                .remove_random(&mut self.rng);
+            if let Some(resource) = stolen {
+                self.player_resources
+                    .get_mut(&command.player)
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?
+                    .deposit([resource])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn play_card(&mut self, command: &Command) -> Result<(), GameError> {
+        if self.card_played {
+            return Err(GameError::new(ErrorCode::ActionNotAllowed, "Only one card can be played each turn."));
+        }
+        let play = command.get_play()?;
+        let hand = self.hands
+            .get(&command.player)
+            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player cards."))?;
+        let card_index = hand.iter().position(|c| *c == play.card())
+            .ok_or_else(|| GameError::new(ErrorCode::CardNotHeld, "You don't have that card."))?;
+
+        match play {
+            Play::Bug => {
+                self.move_scorpion(command)?;
+                let bugs = self.bugs
+                    .get_mut(&command.player)
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player bugs"))?;
+                *bugs += 1;
+                self.has_most_bugs = find_most_bugs(&self.bugs, &self.has_most_bugs, self.config.rules.most_bugs_threshold);
+            },
+            Play::RoadBuilding => {
+                let roads = command.get_all(Target::Road);
+                if roads.is_empty() || roads.len() > 2 {
+                    return Err(GameError::new(ErrorCode::InvalidTarget, "Must select one or two roads to build."));
+                }
+                // Build on a copy so a bad second road doesn't leave the first one behind
+                let mut built = self.board.roads.clone();
+                for road in roads {
+                    build_road(road, command.player.clone(), &self.board.nodes, &mut built, &self.board.adjacency, false)?;
+                }
+                self.board.roads = built;
+                self.has_longest_road = find_longest_road(&self.board, &self.players, &self.has_longest_road, self.config.rules.longest_road_threshold);
+            },
+            Play::YearOfPlenty(first, second) => {
+                self.player_resources
+                    .get_mut(&command.player)
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?
+                    .deposit([first, second])?;
+            },
+            Play::Monopoly(resource) => {
+                let mut taken = 0;
+                for (key, resources) in self.player_resources.iter_mut() {
+                    if *key == command.player { continue; }
+                    while resources.deduct([resource]).is_ok() { taken += 1; }
+                }
+                let resources = self.player_resources
+                    .get_mut(&command.player)
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
+                for _ in 0..taken { resources.deposit([resource])?; }
+            }
+        }
+
+        if let Some(hand) = self.hands.get_mut(&command.player) { hand.remove(card_index); }
+        self.card_played = true;
+        self.find_the_winner();
+
+        Ok(())
+    }
+
+    // Everyone holding too many cards when a seven is rolled loses half of them at random
+    fn discard_half(&mut self) {
+        // Go in seating order so a replay draws the same cards
+        for player in self.players.list.iter() {
+            if let Some(resources) = self.player_resources.get_mut(&player.key) {
+                if resources.count() > u32::from(self.config.rules.discard_limit) {
+                    for _ in 0..resources.count() / 2 { resources.remove_random(&mut self.rng); }
+                }
+            }
+        }
+    }
+
+    // Trades can only be arranged while the active player could also trade with the bank
+    fn check_trading_open(&self) -> Result<(), GameError> {
+        let roll_sum = self.roll_result.0 + self.roll_result.1;
+        if self.phase != Phase::Play || !next_allowed_actions(&self.last_action, roll_sum).contains(&Actions::OfferTrade) {
+            return Err(GameError::new(ErrorCode::ActionNotAllowed, "Trades can't be negotiated right now."));
+        }
+
+        Ok(())
+    }
+
+    fn check_offer(&self, key: &str, bundle: &trading::Bundle) -> Result<(), GameError> {
+        if bundle.give.count() == 0 || bundle.get.count() == 0 {
+            return Err(GameError::new(ErrorCode::MissingTrade, "Both sides of a trade need some resources."));
+        }
+        // Nobody could ever hand over more than everyone holds between them
+        let held: u32 = self.player_resources.values().map(|list| list.count()).sum();
+        if bundle.get.count() > held {
+            return Err(GameError::new(ErrorCode::InvalidTrade, "Nobody holds that many resources."));
+        }
+        let mut resources = *self.player_resources
+            .get(key)
+            .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
+
+        resources.withdraw(bundle.give)
+    }
+
+    fn negotiate(&mut self, command: Command) -> Result<&mut HexagonIsland, GameError> {
+        self.check_trading_open()?;
+
+        let (num_offers, offer_id) = command.get_first(Target::Offer);
+        if num_offers != 1 {
+            return Err(GameError::new(ErrorCode::InvalidTarget, "Must select one trade offer."));
+        }
+        let offer: Offer = self.offers.get(offer_id)
+            .ok_or_else(|| {
+                GameError::new(ErrorCode::InvalidIndex, "There is no such trade offer.")
+                    .with_context(ErrorContext::Offer(offer_id))
+            })?
+            .clone();
+
+        // Whoever made an offer can always take it back
+        if command.action == Actions::RejectTrade && offer.from == command.player {
+            self.offers.remove(offer_id);
+            return Ok(self);
+        }
+
+        if !offer.can_answer(&command.player) {
+            return Err(
+                GameError::new(ErrorCode::InvalidTarget, "That trade offer isn't yours to answer.")
+                    .with_context(ErrorContext::Offer(offer_id))
+            );
+        }
+
+        match command.action {
+            Actions::AcceptTrade => {
+                // Work on copies so the trade either happens completely or not at all
+                let mut offerer = *self.player_resources
+                    .get(&offer.from)
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
+                let mut accepter = *self.player_resources
+                    .get(&command.player)
+                    .ok_or_else(|| GameError::new(ErrorCode::PlayerNotFound, "Can't get player resources."))?;
+
+                offerer.withdraw(offer.bundle.give)?;
+                accepter.withdraw(offer.bundle.get)?;
+                offerer.add(offer.bundle.get);
+                accepter.add(offer.bundle.give);
+
+                self.player_resources.insert(offer.from.clone(), offerer);
+                self.player_resources.insert(command.player.clone(), accepter);
+                self.offers.remove(offer_id);
+            },
+            Actions::RejectTrade => {
+                match offer.to {
+                    Some(_) => { self.offers.remove(offer_id); },
+                    None => {
+                        if let Some(open) = self.offers.get_mut(offer_id) { open.declined.push(command.player.clone()); }
+                    }
+                }
+            },
+            Actions::CounterTrade => {
+                let bundle = command.get_offer()?;
+                self.check_offer(&command.player, &bundle)?;
+                self.offers.propose(&command.player, Some(offer.from.clone()), bundle);
+            },
+            _ => return Err(GameError::new(ErrorCode::ActionNotAllowed, "That is not an allowed action right now."))
+        }
+
+        Ok(self)
     }
 }
 
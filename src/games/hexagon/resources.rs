@@ -1,5 +1,8 @@
+use rand::Rng;
 use serde::{Serialize, Deserialize};
 
+use crate::games::core::error::{ GameError, ErrorCode, ErrorContext };
+
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Resource {
     Block,
@@ -10,7 +13,8 @@ pub enum Resource {
     Desert
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ResourceList {
     pub block: u16,
     pub rock: u16,
@@ -21,6 +25,17 @@ pub struct ResourceList {
 
 type ResourceArray<const N: usize> = [Resource; N];
 
+impl Resource {
+    pub fn context(self) -> ErrorContext {
+        ErrorContext::Resource(format!("{:?}", self))
+    }
+}
+
+fn not_enough(resource: Resource) -> GameError {
+    GameError::new(ErrorCode::NotEnoughResources, "Can't deduct; not enough resources.")
+        .with_context(resource.context())
+}
+
 impl ResourceList {
     pub fn new() -> ResourceList {
         ResourceList { block: 0, rock: 0, timber: 0, fiber: 0, cereal: 0 }
@@ -36,11 +51,12 @@ impl ResourceList {
         ]
     }
 
-    pub fn count(&self) -> u16 {
-        self.block + self.rock + self.timber + self.fiber + self.cereal
+    // Summed wider than the fields, since a bundle from a client can hold anything a u16 can
+    pub fn count(&self) -> u32 {
+        self.to_array().iter().map(|&(_, amnt)| u32::from(amnt)).sum()
     }
 
-    pub fn deposit<const N: usize>(&mut self, resources: ResourceArray<N>) -> Result<(),&'static str> {
+    pub fn deposit<const N: usize>(&mut self, resources: ResourceArray<N>) -> Result<(),GameError> {
         for resource in resources {
             match resource {
                 Resource::Block => self.block += 1,
@@ -48,64 +64,126 @@ impl ResourceList {
                 Resource::Timber => self.timber += 1,
                 Resource::Fiber => self.fiber += 1,
                 Resource::Cereal => self.cereal += 1,
-                Resource::Desert => return Err("Can't deposit Desert resources.")
+                Resource::Desert => return Err(
+                    GameError::new(ErrorCode::InvalidResource, "Can't deposit Desert resources.")
+                        .with_context(resource.context())
+                )
             }
         }
 
         Ok(())
     }
 
-    pub fn deduct<const N: usize>(&mut self, resources: ResourceArray<N>) -> Result<(),&'static str> {
+    pub fn deduct<const N: usize>(&mut self, resources: ResourceArray<N>) -> Result<(),GameError> {
         for resource in resources {
             match resource {
                 Resource::Block => 
                     if self.block > 0 { self.block -= 1 } 
-                    else { return Err("Can't deduct; not enough resources.") },
+                    else { return Err(not_enough(resource)) },
                 Resource::Rock => 
                     if self.rock > 0 { self.rock -= 1 } 
-                    else { return Err("Can't deduct; not enough resources.") },
+                    else { return Err(not_enough(resource)) },
                 Resource::Timber => 
                     if self.timber > 0 { self.timber -= 1 } 
-                    else { return Err("Can't deduct; not enough resources.") },
+                    else { return Err(not_enough(resource)) },
                 Resource::Fiber => 
                     if self.fiber > 0 { self.fiber -= 1 } 
-                    else { return Err("Can't deduct; not enough resources.") },
+                    else { return Err(not_enough(resource)) },
                 Resource::Cereal => 
                     if self.cereal > 0 { self.cereal -= 1 } 
-                    else { return Err("Can't deduct; not enough resources.") },
+                    else { return Err(not_enough(resource)) },
                 Resource::Desert => 
-                    return Err("Can't deduct Desert resources.")
+                    return Err(
+                        GameError::new(ErrorCode::InvalidResource, "Can't deduct Desert resources.")
+                            .with_context(resource.context())
+                    )
             }
         }
 
         Ok(())
     }
 
-    pub fn check<const N: usize>(&mut self, resources: ResourceArray<N>) -> Result<(),&'static str> {
+    pub fn check<const N: usize>(&mut self, resources: ResourceArray<N>) -> Result<(),GameError> {
 
         let mut the_bill = ResourceList::new();
         let _status = the_bill.deposit(resources);
         let bill_array = the_bill.to_array();
         
-        let mut can_pay_the_bill = true;
+        // Report the first resource that comes up short
+        let mut short_of = None;
         for (rsrc,amnt) in bill_array {
-            match rsrc {
-                Resource::Block => if self.block < amnt { can_pay_the_bill = false },
-                Resource::Rock => if self.rock < amnt { can_pay_the_bill = false },
-                Resource::Timber => if self.timber < amnt { can_pay_the_bill = false },
-                Resource::Fiber => if self.fiber < amnt { can_pay_the_bill = false },
-                Resource::Cereal => if self.cereal < amnt { can_pay_the_bill = false },
-                Resource::Desert => ()
+            let have = match rsrc {
+                Resource::Block => self.block,
+                Resource::Rock => self.rock,
+                Resource::Timber => self.timber,
+                Resource::Fiber => self.fiber,
+                Resource::Cereal => self.cereal,
+                Resource::Desert => amnt
+            };
+            if have < amnt && short_of.is_none() { short_of = Some(rsrc); }
+        }
+
+        match short_of {
+            None => Ok(()),
+            Some(rsrc) => Err(
+                GameError::new(ErrorCode::NotEnoughResources, "Not enough resources to build.")
+                    .with_context(rsrc.context())
+            )
+        }
+    }
+
+    /// Checks that everything in `other` is here without taking any of it
+    pub fn covers(&self, other: ResourceList) -> Result<(),GameError> {
+        for ((rsrc, have), (_, amnt)) in self.to_array().into_iter().zip(other.to_array()) {
+            if have < amnt { return Err(not_enough(rsrc)); }
+        }
+
+        Ok(())
+    }
+
+    /// Takes out everything in `other`, or nothing at all if any of it is missing
+    pub fn withdraw(&mut self, other: ResourceList) -> Result<(),GameError> {
+        self.covers(other)?;
+        self.block -= other.block;
+        self.rock -= other.rock;
+        self.timber -= other.timber;
+        self.fiber -= other.fiber;
+        self.cereal -= other.cereal;
+
+        Ok(())
+    }
+
+    /// Takes out one card picked at random, if there are any
+    pub fn remove_random<R: Rng>(&mut self, rng: &mut R) -> Option<Resource> {
+        let count = self.count();
+        if count == 0 { return None; }
+
+        let mut pick = rng.gen_range(0..count);
+        for (rsrc, amnt) in self.to_array() {
+            let amnt = u32::from(amnt);
+            if pick < amnt {
+                self.deduct([rsrc]).ok()?;
+                return Some(rsrc);
             }
+            pick -= amnt;
         }
 
-        if can_pay_the_bill { Ok(()) }
-        else { Err("Not enough resources to build.") }
+        None
+    }
+
+    pub fn add(&mut self, other: ResourceList) {
+        self.block += other.block;
+        self.rock += other.rock;
+        self.timber += other.timber;
+        self.fiber += other.fiber;
+        self.cereal += other.cereal;
     }
 
-    pub fn trade(&mut self, have: Resource, want: Resource) -> Result<(),&'static str> {
-        self.check([have;3])?;
-        self.deduct([have;3])?;
+    /// Swaps `ratio` of one resource for one of another
+    pub fn trade(&mut self, have: Resource, want: Resource, ratio: u16) -> Result<(),GameError> {
+        let mut cost = ResourceList::new();
+        for _ in 0..ratio { cost.deposit([have])?; }
+        self.withdraw(cost)?;
         self.deposit([want])?;
         Ok(())
     }
@@ -139,25 +217,46 @@ fn resource_lists() {
         (0, 0, 0, 1, 1)
     );
 
+    let full = ResourceList { block: u16::MAX, rock: u16::MAX, timber: u16::MAX, fiber: u16::MAX, cereal: u16::MAX };
+    assert_eq!(full.count(), 5 * u32::from(u16::MAX));
+
 }
 
 #[test]
 fn resource_list_errors() {
     let mut resource_list = ResourceList::new();
     let attempt = resource_list.deposit([Resource::Desert]);
-    assert_eq!(attempt, Err("Can't deposit Desert resources."));
+    assert_eq!(
+        attempt,
+        Err(GameError::new(ErrorCode::InvalidResource, "Can't deposit Desert resources.")
+            .with_context(ErrorContext::Resource(String::from("Desert"))))
+    );
     let attempt = resource_list.deduct([Resource::Desert]);
-    assert_eq!(attempt, Err("Can't deduct Desert resources."));
+    assert_eq!(
+        attempt,
+        Err(GameError::new(ErrorCode::InvalidResource, "Can't deduct Desert resources.")
+            .with_context(ErrorContext::Resource(String::from("Desert"))))
+    );
+    let attempt = resource_list.deduct([Resource::Rock]);
+    assert_eq!(
+        attempt,
+        Err(GameError::new(ErrorCode::NotEnoughResources, "Can't deduct; not enough resources.")
+            .with_context(ErrorContext::Resource(String::from("Rock"))))
+    );
 }
 
 #[test]
 fn credit_check() {
     let mut resource_list = ResourceList::new();
     let check = resource_list.check([Resource::Block, Resource::Block, Resource::Timber]);
-    assert_eq!(check, Err("Not enough resources to build."));
+    assert_eq!(
+        check,
+        Err(GameError::new(ErrorCode::NotEnoughResources, "Not enough resources to build.")
+            .with_context(ErrorContext::Resource(String::from("Block"))))
+    );
     let _status = resource_list.deposit([Resource::Block, Resource::Timber]);
     let check = resource_list.check([Resource::Block, Resource::Block, Resource::Timber]);
-    assert_eq!(check, Err("Not enough resources to build."));
+    assert_eq!(check.unwrap_err().code, ErrorCode::NotEnoughResources);
     let _status = resource_list.deposit([Resource::Block]);
     let check = resource_list.check([Resource::Block, Resource::Block, Resource::Timber]);
     assert_eq!(check, Ok(()));
@@ -181,7 +280,7 @@ fn trade_resources() {
         ]
     );
 
-    resource_list.trade(Resource::Fiber, Resource::Rock).unwrap();
+    resource_list.trade(Resource::Fiber, Resource::Rock, 3).unwrap();
     assert_eq!(
         resource_list.to_array(),
         [
@@ -0,0 +1,121 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::Error;
+
+use crate::games::core::error::{ GameError, ErrorCode };
+use super::resources::{ Resource, ResourceList };
+
+/// House rules; anything left out falls back to the standard game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    #[serde(deserialize_with = "deserialize_number")]
+    pub discard_limit: u16,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub bank_ratio: u16,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub most_bugs_threshold: u8,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub most_bugs_bonus: u8,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub longest_road_threshold: u8,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub longest_road_bonus: u8,
+    #[serde(deserialize_with = "deserialize_cost")]
+    pub road_cost: ResourceList,
+    #[serde(deserialize_with = "deserialize_cost")]
+    pub village_cost: ResourceList,
+    #[serde(deserialize_with = "deserialize_cost")]
+    pub town_cost: ResourceList,
+    #[serde(deserialize_with = "deserialize_cost")]
+    pub card_cost: ResourceList,
+    /// Seconds each turn can take before it is played out automatically; zero means no limit
+    #[serde(deserialize_with = "deserialize_number")]
+    pub turn_time_limit: u32,
+    /// How many turns in a row a player can run out of time on before they are marked idle
+    #[serde(deserialize_with = "deserialize_number")]
+    pub idle_turn_limit: u8
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            discard_limit: 7,
+            bank_ratio: 3,
+            most_bugs_threshold: 3,
+            most_bugs_bonus: 2,
+            longest_road_threshold: 3,
+            longest_road_bonus: 2,
+            road_cost: ResourceList { block: 1, timber: 1, ..ResourceList::new() },
+            village_cost: ResourceList { block: 1, timber: 1, fiber: 1, cereal: 1, ..ResourceList::new() },
+            town_cost: ResourceList { rock: 3, cereal: 2, ..ResourceList::new() },
+            card_cost: ResourceList { rock: 1, fiber: 1, cereal: 1, ..ResourceList::new() },
+            turn_time_limit: 0,
+            idle_turn_limit: 2
+        }
+    }
+}
+
+impl Rules {
+    pub fn validate(&self) -> Result<(), GameError> {
+        let invalid = |message: &str| Err(GameError::new(ErrorCode::InvalidConfig, message));
+
+        if self.bank_ratio == 0 { return invalid("The bank ratio has to be at least one."); }
+        if self.most_bugs_threshold == 0 { return invalid("The most bugs threshold has to be at least one."); }
+        if self.longest_road_threshold == 0 { return invalid("The longest road threshold has to be at least one."); }
+        if self.idle_turn_limit == 0 { return invalid("The idle turn limit has to be at least one."); }
+        let costs = [&self.road_cost, &self.village_cost, &self.town_cost, &self.card_cost];
+        if costs.iter().any(|cost| cost.count() == 0) { return invalid("Nothing can be free to build or buy."); }
+
+        Ok(())
+    }
+}
+
+// Flattened form fields all arrive as text, so numbers have to be parsed by hand
+fn deserialize_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number<T> {
+        Number(T),
+        Text(String)
+    }
+
+    match Number::<T>::deserialize(deserializer)? {
+        Number::Number(number) => Ok(number),
+        Number::Text(text) => text.trim().parse().map_err(D::Error::custom)
+    }
+}
+
+// Costs are either a resource list or, from a form, resource names like "rock rock cereal"
+fn deserialize_cost<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ResourceList, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Cost {
+        List(ResourceList),
+        Text(String)
+    }
+
+    match Cost::deserialize(deserializer)? {
+        Cost::List(list) => Ok(list),
+        Cost::Text(text) => text.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .try_fold(ResourceList::new(), |mut cost, name| {
+                let resource = match name.to_lowercase().as_str() {
+                    "block" => Resource::Block,
+                    "rock" => Resource::Rock,
+                    "timber" => Resource::Timber,
+                    "fiber" => Resource::Fiber,
+                    "cereal" => Resource::Cereal,
+                    _ => return Err(D::Error::custom(format!("unknown resource {}", name)))
+                };
+                cost.deposit([resource]).map_err(D::Error::custom)?;
+                Ok(cost)
+            })
+    }
+}
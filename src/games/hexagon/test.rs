@@ -1,4 +1,9 @@
 use super::*;
+use rand::thread_rng;
+use trading::Bundle;
+use cards::{ Card, Play };
+use board::MapTile;
+use proptest::prelude::*;
 // use serde_json::to_string;
 
 #[test]
@@ -18,14 +23,28 @@ fn initial_state() {
             config: Config {
                 num_players: 2,
                 score_to_win: 10,
-                game_board_width: 5
+                game_board_width: 5,
+                seed: None,
+                map: None,
+                number_placement: NumberPlacement::Random,
+                rules: Rules::default()
             },
             roll_result: (0,0), 
             player_colors: HashMap::new(),
             player_resources: HashMap::new(),
             bugs: HashMap::new(),
+            has_most_bugs: None,
+            has_longest_road: None,
             board: GameBoard::new(),
-            the_winner: None
+            the_winner: None,
+            offers: Offers::new(),
+            deck: Vec::new(),
+            hands: HashMap::new(),
+            card_played: false,
+            log: ActionLog::new(),
+            turn_started: TurnClock::default(),
+            idle_turns: HashMap::new(),
+            rng: GameRng::seed_from_u64(0)
         }
     )
 }
@@ -39,7 +58,11 @@ fn game_configuration() {
     let config = Config {
         num_players,
         score_to_win,
-        game_board_width
+        game_board_width,
+        seed: None,
+        map: None,
+        number_placement: NumberPlacement::Random,
+        rules: Rules::default()
     };
     game.configure_game(config).unwrap();
     assert_eq!(game.config.num_players, 4);
@@ -49,9 +72,13 @@ fn game_configuration() {
     let attempt = game.configure_game(Config {
         num_players: 2,
         score_to_win: 7,
-        game_board_width: 7
+        game_board_width: 7,
+        seed: None,
+        map: None,
+        number_placement: NumberPlacement::Random,
+        rules: Rules::default()
     });
-    assert_eq!(attempt, Err("Cannot configure game outside of boot phase!"));
+    assert_eq!(attempt, Err(GameError::new(ErrorCode::WrongPhase, "Cannot configure game outside of boot phase!")));
 }
 
 #[test]
@@ -60,10 +87,14 @@ fn should_reset() {
     let config = Config {
         num_players: 2,
         score_to_win: 10,
-        game_board_width: 5
+        game_board_width: 5,
+        seed: None,
+        map: None,
+        number_placement: NumberPlacement::Random,
+        rules: Rules::default()
     };
     game.configure_game(config).unwrap();
-    game.board.setup(5);
+    game.board.setup(&BoardMap::hexagon(5), NumberPlacement::Random, &mut thread_rng()).unwrap();
 
     game.reset();
     assert_eq!(
@@ -80,14 +111,28 @@ fn should_reset() {
             config: Config {
                 num_players: 2,
                 score_to_win: 10,
-                game_board_width: 5
+                game_board_width: 5,
+                seed: None,
+                map: None,
+                number_placement: NumberPlacement::Random,
+                rules: Rules::default()
             },
             roll_result: (0,0),
             player_colors: HashMap::new(),
             player_resources: HashMap::new(),
             bugs: HashMap::new(),
+            has_most_bugs: None,
+            has_longest_road: None,
             board: GameBoard::new(),
-            the_winner: None
+            the_winner: None,
+            offers: Offers::new(),
+            deck: Vec::new(),
+            hands: HashMap::new(),
+            card_played: false,
+            log: ActionLog::new(),
+            turn_started: TurnClock::default(),
+            idle_turns: HashMap::new(),
+            rng: GameRng::seed_from_u64(0)
         }
     )
 }
@@ -105,7 +150,9 @@ fn can_roll_the_dice() {
         action: Actions::RollDice,
         player: String::from("key1"),
         target: [None; 5],
-        trade: None
+        trade: None,
+        offer: None,
+        play: None
     };
     game.process_action(command).unwrap();
     assert!(game.roll_result != (0,0));
@@ -117,7 +164,11 @@ fn player_color() {
     let config = Config {
         num_players: 2,
         score_to_win: 10,
-        game_board_width: 5
+        game_board_width: 5,
+        seed: None,
+        map: None,
+        number_placement: NumberPlacement::Random,
+        rules: Rules::default()
     };
     game.configure_game(config).unwrap();
 
@@ -139,7 +190,7 @@ fn too_many_players() {
     game.add_player("key1", "name1").unwrap();
     game.add_player("key2", "name2").unwrap();
     let attempt = game.add_player("key3", "name3");
-    assert_eq!(attempt, Err("Cannot add player; exceeds maximum number of players."));
+    assert_eq!(attempt, Err(GameError::new(ErrorCode::GameFull, "Cannot add player; exceeds maximum number of players.")));
 }
 
 fn game_setup() -> HexagonIsland {
@@ -147,7 +198,13 @@ fn game_setup() -> HexagonIsland {
     let config = Config {
         num_players: 2,
         score_to_win: 4,
-        game_board_width: 5
+        game_board_width: 5,
+        seed: None,
+        map: None,
+        number_placement: NumberPlacement::Random,
+        // High enough that a stray seven doesn't throw away what the tests build with,
+        // and that the first round's roads don't already win the longest road
+        rules: Rules { discard_limit: 20, longest_road_threshold: 5, ..Rules::default() }
     };
     game.configure_game(config).unwrap();
 
@@ -167,7 +224,17 @@ fn game_setup() -> HexagonIsland {
     );
     command.target[0] = Some( (Target::Node, 0) );
     let attempt = game.process_action(command);
-    assert_eq!(attempt, Err("Must select one node and one road during setup."));
+    assert_eq!(attempt, Err(GameError::new(ErrorCode::InvalidTarget, "Must select one node and one road during setup.")));
+
+    let mut command = Command::new(
+        Actions::PlaceVillageAndRoad,
+        String::from("key1")
+    );
+    command.target[0] = Some( (Target::Node, 0) );
+    command.target[1] = Some( (Target::Road, 1000) );
+    let error = game.process_action(command).unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidIndex);
+    assert_eq!(error.context, Some(ErrorContext::Road(1000)));
 
     let active_player = game.players.active_player.as_ref().unwrap();
     assert_eq!(active_player.key, String::from("key1"));
@@ -350,6 +417,13 @@ fn game_progression() {
     assert_eq!(active_player.key, String::from("key1"));
 
     assert_eq!(game.round, 2);
+
+    // Everyone can see how long each player's road is
+    let status = game.get_game_status("key2");
+    for (score, key) in status.scoreboard.iter().zip(["key1", "key2"]) {
+        assert!(score.longest_road >= 2);
+        assert_eq!(score.longest_road, measure_longest_road(&game.board, key));
+    }
 }
 
 #[test]
@@ -361,7 +435,7 @@ fn action_errors() {
         String::from("key1")
     );
     let attempt = game.process_action(command);
-    assert_eq!(attempt, Err("That is not an allowed action right now."));
+    assert_eq!(attempt, Err(GameError::new(ErrorCode::ActionNotAllowed, "That is not an allowed action right now.")));
 }
 
 #[test]
@@ -435,13 +509,757 @@ fn move_scorpion_when_seven_is_rolled() {
     game.process_action(command).unwrap();
     game.roll_result = (3,4); // Intentionally roll a seven
 
+    // The scorpion can't stay where it is
+    let mut command = Command::new(
+        Actions::MoveScorpion,
+        String::from("key1")
+    );
+    command.target[0] = Some( (Target::Hex, desert_index) );
+    assert_eq!(game.process_action(command).unwrap_err().code, ErrorCode::InvalidTarget);
+
+    // Pick somewhere nobody else has built so there is no one to steal from
+    let hex_index = (0..game.board.hexagons.len()).find(
+        | &hex | hex != desert_index && game.board.find_neighboring_nodes(hex).iter().all(
+            | &n | game.board.nodes[n].player_key.as_deref() != Some("key2")
+        )
+    ).unwrap();
     let mut command = Command::new(
         Actions::MoveScorpion,
         String::from("key1")
     );
-    command.target[0] = Some( (Target::Hex, 0) );
+    command.target[0] = Some( (Target::Hex, hex_index) );
     game.process_action(command).unwrap();
 
-    assert_eq!(game.board.scorpion_index.unwrap(), 0);
+    assert_eq!(game.board.scorpion_index.unwrap(), hex_index);
     
-}
\ No newline at end of file
+}
+
+#[test]
+fn scorpion_steals_and_sevens_discard() {
+    let mut game = play_round_one(game_setup());
+    game.config.rules.discard_limit = 7;
+    game.player_resources.insert(String::from("key1"), ResourceList { block: 4, rock: 4, ..ResourceList::new() });
+    game.player_resources.insert(String::from("key2"), ResourceList { timber: 3, ..ResourceList::new() });
+
+    // Keep rolling until a seven comes up
+    loop {
+        game.last_action = Actions::EndTurn;
+        game.process_action(Command::new(Actions::RollDice, String::from("key1"))).unwrap();
+        if game.roll_result.0 + game.roll_result.1 == 7 { break; }
+        game.player_resources.insert(String::from("key1"), ResourceList { block: 4, rock: 4, ..ResourceList::new() });
+        game.player_resources.insert(String::from("key2"), ResourceList { timber: 3, ..ResourceList::new() });
+    }
+
+    // Eight cards is over the limit of seven, three is not
+    assert_eq!(game.player_resources["key1"].count(), 4);
+    assert_eq!(game.player_resources["key2"].count(), 3);
+
+    // Moving next to the other player means stealing from them
+    let hex_index = (0..game.board.hexagons.len()).find(
+        | &hex | Some(hex) != game.board.scorpion_index && game.board.find_neighboring_nodes(hex).iter().any(
+            | &n | game.board.nodes[n].player_key.as_deref() == Some("key2")
+        )
+    ).unwrap();
+    let mut command = Command::new(Actions::MoveScorpion, String::from("key1"));
+    command.target[0] = Some( (Target::Hex, hex_index) );
+    assert_eq!(game.process_action(command.clone()).unwrap_err().code, ErrorCode::InvalidTarget);
+
+    command.target[1] = Some( (Target::Player, 0) );
+    assert_eq!(game.process_action(command.clone()).unwrap_err().code, ErrorCode::InvalidTarget);
+
+    command.target[1] = Some( (Target::Player, 1) );
+    game.process_action(command).unwrap();
+    assert_eq!(game.board.scorpion_index, Some(hex_index));
+    assert_eq!(game.player_resources["key1"].count(), 5);
+    assert_eq!(game.player_resources["key1"].timber, 1);
+    assert_eq!(game.player_resources["key2"].count(), 2);
+}
+
+#[test]
+fn should_log_accepted_actions() {
+    let mut game = game_setup();
+
+    let num_events = game.log.events.len();
+    assert_eq!(num_events, 10); // Two joins and eight setup actions
+    assert!(game.log.board_seed.is_some());
+    assert_eq!(
+        game.log.events[0],
+        Event::Joined { key: String::from("key1"), name: String::from("name1") }
+    );
+
+    // Rejected commands are not recorded
+    let command = Command::new(
+        Actions::EndTurn,
+        String::from("key2")
+    );
+    assert!(game.process_action(command).is_err());
+    assert_eq!(game.log.events.len(), num_events);
+
+    // The dice are recorded along with the command
+    let command = Command::new(
+        Actions::RollDice,
+        String::from("key1")
+    );
+    game.process_action(command.clone()).unwrap();
+    assert_eq!(
+        game.log.events[num_events],
+        Event::Acted { command, roll_result: Some(game.roll_result) }
+    );
+}
+
+#[test]
+fn should_replay_the_log() {
+    let mut game = game_setup();
+
+    for player in ["key1", "key2", "key1", "key2"] {
+        let command = Command::new(
+            Actions::RollDice,
+            String::from(player)
+        );
+        game.process_action(command).unwrap();
+
+        if game.roll_result.0 + game.roll_result.1 == 7 {
+            let mut command = Command::new(
+                Actions::MoveScorpion,
+                String::from(player)
+            );
+            let hex_index = if game.board.scorpion_index == Some(0) { 1 } else { 0 };
+            command.target[0] = Some( (Target::Hex, hex_index) );
+            // Steal from whoever is there so the replay has to draw the same card
+            let victim = game.board.find_neighboring_nodes(hex_index).iter()
+                .filter_map(|&n| game.board.nodes[n].player_key.clone())
+                .find(|k| k != player);
+            if let Some(victim) = victim {
+                command.target[1] = Some( (Target::Player, game.public_id(&victim).unwrap()) );
+            }
+            game.process_action(command).unwrap();
+        }
+
+        let command = Command::new(
+            Actions::EndTurn,
+            String::from(player)
+        );
+        game.process_action(command).unwrap();
+    }
+
+    let replayed = HexagonIsland::replay(game.config.clone(), &game.log).unwrap();
+    assert!(replayed.eq_ignoring_clock(&game));
+}
+
+#[test]
+fn same_seed_same_game() {
+    let play = | seed: Option<u64> | {
+        let mut game = HexagonIsland::new();
+        game.configure_game(Config {
+            num_players: 2,
+            score_to_win: 10,
+            game_board_width: 5,
+            seed,
+            map: None,
+            number_placement: NumberPlacement::Random,
+            rules: Rules::default()
+        }).unwrap();
+        game.add_player("key1", "name1").unwrap()
+            .add_player("key2", "name2").unwrap();
+        let rolls: Vec<(u8,u8)> = (0..20).map(|_| roll_dice(&mut game.rng)).collect();
+        (game, rolls)
+    };
+
+    let (game1, rolls1) = play(Some(1234));
+    let (game2, rolls2) = play(Some(1234));
+    assert_eq!(game1.board, game2.board);
+    assert_eq!(rolls1, rolls2);
+
+    // Without a seed one gets picked and kept so the game can be recreated
+    let (mut game3, rolls3) = play(None);
+    let seed = game3.config.seed.unwrap();
+    let (game4, rolls4) = play(Some(seed));
+    assert_eq!(game3.board, game4.board);
+    assert_eq!(rolls3, rolls4);
+
+    // Players only get to see it once there is nothing left to predict
+    assert_eq!(game3.get_game_status("key1").seed, None);
+    game3.phase = Phase::End;
+    assert_eq!(game3.get_game_status("key1").seed, Some(seed));
+}
+
+#[test]
+fn seed_from_form() {
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5, \"seed\": \"\"}"
+    ).unwrap();
+    assert_eq!(config.seed, None);
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5, \"seed\": \"42\"}"
+    ).unwrap();
+    assert_eq!(config.seed, Some(42));
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5, \"seed\": 42}"
+    ).unwrap();
+    assert_eq!(config.seed, Some(42));
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5}"
+    ).unwrap();
+    assert_eq!(config.seed, None);
+    let attempt = serde_json::from_str::<Config>(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5, \"seed\": \"abc\"}"
+    );
+    assert!(attempt.is_err());
+}
+
+#[test]
+fn status_hides_other_keys() {
+    let game = play_round_one(game_setup());
+    let status = game.get_game_status("key1");
+
+    assert_eq!(status.key, Some(String::from("key1")));
+    assert_eq!(status.id, Some(0));
+    assert_eq!(status.players.len(), 2);
+    assert_eq!(status.players[1].name, "name2");
+    assert_eq!(status.players[1].cards, game.player_resources["key2"].count());
+    assert!(status.board.nodes.iter().any(|n| n.owner == Some(1)));
+
+    // No other player's key shows up anywhere in the serialized status
+    let serialized = serde_json::to_string(&status).unwrap();
+    assert!(serialized.contains("key1"));
+    assert!(!serialized.contains("key2"));
+
+    // Someone who isn't playing doesn't get a key echoed back
+    let status = game.get_game_status("not_a_key");
+    assert_eq!(status.key, None);
+    assert_eq!(status.id, None);
+}
+
+#[test]
+fn trade_between_players() {
+    let mut game = play_round_one(game_setup());
+    game.last_action = Actions::RollDice;
+    game.roll_result = (3,3);
+    game.player_resources.insert(String::from("key1"), ResourceList { block: 2, rock: 0, timber: 0, fiber: 0, cereal: 0 });
+    game.player_resources.insert(String::from("key2"), ResourceList { block: 0, rock: 1, timber: 1, fiber: 0, cereal: 0 });
+
+    let offer = |action, player: &str, target: Option<usize>, bundle: Option<Bundle>| {
+        let mut command = Command::new(action, String::from(player));
+        command.target[0] = target.map(|id| (Target::Offer, id));
+        command.offer = bundle;
+        command
+    };
+    let two_block_for_rock = Bundle {
+        give: ResourceList { block: 2, ..ResourceList::new() },
+        get: ResourceList { rock: 1, ..ResourceList::new() }
+    };
+
+    // Only the active player can put an offer on the table, and only one they can pay for
+    assert_eq!(
+        game.process_action(offer(Actions::OfferTrade, "key2", None, Some(two_block_for_rock))).unwrap_err().code,
+        ErrorCode::NotYourTurn
+    );
+    let too_much = Bundle { give: ResourceList { block: 3, ..ResourceList::new() }, ..two_block_for_rock };
+    assert_eq!(
+        game.process_action(offer(Actions::OfferTrade, "key1", None, Some(too_much))).unwrap_err().code,
+        ErrorCode::NotEnoughResources
+    );
+    // Asking for more than anyone has is turned down rather than overflowing the count
+    let impossible = Bundle { get: ResourceList { block: u16::MAX, rock: 1, ..ResourceList::new() }, ..two_block_for_rock };
+    assert_eq!(
+        game.process_action(offer(Actions::OfferTrade, "key1", None, Some(impossible))).unwrap_err().code,
+        ErrorCode::InvalidTrade
+    );
+    game.process_action(offer(Actions::OfferTrade, "key1", None, Some(two_block_for_rock))).unwrap();
+    assert!(game.get_game_status("key2").allowed_actions.contains(&Actions::AcceptTrade));
+    assert!(!game.get_game_status("key1").allowed_actions.contains(&Actions::AcceptTrade));
+
+    // The other player counters, and the counter goes back to whoever made the offer
+    let block_for_timber = Bundle {
+        give: ResourceList { timber: 1, ..ResourceList::new() },
+        get: ResourceList { block: 1, ..ResourceList::new() }
+    };
+    game.process_action(offer(Actions::CounterTrade, "key2", Some(0), Some(block_for_timber))).unwrap();
+    assert_eq!(game.offers.get(1).unwrap().to, Some(String::from("key1")));
+    assert_eq!(
+        game.process_action(offer(Actions::AcceptTrade, "key2", Some(1), None)).unwrap_err().code,
+        ErrorCode::InvalidTarget
+    );
+
+    // Accepting moves both bundles at once
+    game.process_action(offer(Actions::AcceptTrade, "key1", Some(1), None)).unwrap();
+    assert_eq!(game.player_resources["key1"], ResourceList { block: 1, timber: 1, ..ResourceList::new() });
+    assert_eq!(game.player_resources["key2"], ResourceList { block: 1, rock: 1, ..ResourceList::new() });
+
+    // The original offer can no longer be paid for, so accepting it changes nothing
+    assert_eq!(
+        game.process_action(offer(Actions::AcceptTrade, "key2", Some(0), None)).unwrap_err().code,
+        ErrorCode::NotEnoughResources
+    );
+    assert_eq!(game.player_resources["key2"], ResourceList { block: 1, rock: 1, ..ResourceList::new() });
+
+    game.process_action(offer(Actions::RejectTrade, "key2", Some(0), None)).unwrap();
+    assert_eq!(game.offers.get(0).unwrap().declined, vec![String::from("key2")]);
+    assert_eq!(game.get_game_status("key1").offers[0].declined, vec![1]);
+
+    // Whatever is left expires when the turn ends
+    game.process_action(Command::new(Actions::EndTurn, String::from("key1"))).unwrap();
+    assert!(game.offers.list.is_empty());
+}
+
+#[test]
+fn buy_and_play_cards() {
+    let mut game = play_round_one(game_setup());
+    assert_eq!(game.deck.len(), 25);
+    game.last_action = Actions::RollDice;
+    game.roll_result = (3,3);
+    game.player_resources.insert(String::from("key1"), ResourceList { rock: 1, fiber: 1, cereal: 1, ..ResourceList::new() });
+    game.player_resources.insert(String::from("key2"), ResourceList { block: 2, timber: 1, ..ResourceList::new() });
+
+    // Buying draws the top card into a hand nobody else gets to see;
+    // a victory point on top would already win a four point game
+    *game.deck.last_mut().unwrap() = Card::Bug;
+    let top = *game.deck.last().unwrap();
+    game.process_action(Command::new(Actions::BuyBug, String::from("key1"))).unwrap();
+    assert_eq!(game.deck.len(), 24);
+    assert_eq!(game.hands["key1"], vec![top]);
+    assert_eq!(game.player_resources["key1"].count(), 0);
+    assert_eq!(game.get_game_status("key1").hand, vec![top]);
+    assert!(game.get_game_status("key2").hand.is_empty());
+    assert_eq!(game.get_game_status("key2").players[0].development_cards, 1);
+    assert!(game.get_game_status("key1").allowed_actions.contains(&Actions::PlayCard));
+
+    // Only cards in hand can be played
+    game.hands.insert(String::from("key1"), vec![Card::Monopoly, Card::YearOfPlenty]);
+    let mut command = Command::new(Actions::PlayCard, String::from("key1"));
+    command.play = Some(Play::RoadBuilding);
+    assert_eq!(game.process_action(command).unwrap_err().code, ErrorCode::CardNotHeld);
+
+    let mut command = Command::new(Actions::PlayCard, String::from("key1"));
+    command.play = Some(Play::Monopoly(Resource::Block));
+    game.process_action(command).unwrap();
+    assert_eq!(game.player_resources["key1"].block, 2);
+    assert_eq!(game.player_resources["key2"].block, 0);
+    assert_eq!(game.hands["key1"], vec![Card::YearOfPlenty]);
+
+    // One card a turn
+    let mut command = Command::new(Actions::PlayCard, String::from("key1"));
+    command.play = Some(Play::YearOfPlenty(Resource::Rock, Resource::Cereal));
+    assert_eq!(game.process_action(command.clone()).unwrap_err().code, ErrorCode::ActionNotAllowed);
+
+    game.process_action(Command::new(Actions::EndTurn, String::from("key1"))).unwrap();
+    game.process_action(Command::new(Actions::RollDice, String::from("key2"))).unwrap();
+    game.roll_result = (3,3);
+    game.process_action(Command::new(Actions::EndTurn, String::from("key2"))).unwrap();
+    game.process_action(Command::new(Actions::RollDice, String::from("key1"))).unwrap();
+    game.roll_result = (3,3);
+    let rock = game.player_resources["key1"].rock;
+    game.process_action(command).unwrap();
+    assert_eq!(game.player_resources["key1"].rock, rock + 1);
+    assert!(game.hands["key1"].is_empty());
+}
+
+#[test]
+fn victory_point_cards_score() {
+    let mut game = play_round_one(game_setup());
+    game.find_the_winner();
+    assert_eq!(game.the_winner, None);
+
+    game.hands.insert(String::from("key2"), vec![Card::VictoryPoint; 6]);
+    game.find_the_winner();
+    assert_eq!(game.the_winner, Some(String::from("key2")));
+}
+
+#[test]
+fn scoreboard_shows_where_points_come_from() {
+    let mut game = play_round_one(game_setup());
+    game.hands.insert(String::from("key2"), vec![Card::VictoryPoint; 2]);
+    game.bugs.insert(String::from("key1"), 3);
+    game.has_most_bugs = Some(String::from("key1"));
+
+    let status = game.get_game_status("key1");
+    let score = &status.scoreboard[0];
+    assert_eq!(score.buildings, score_player_nodes("key1", &game.board.nodes));
+    assert_eq!(score.bonuses, vec![Bonus { source: BonusSource::MostBugs, points: game.config.rules.most_bugs_bonus }]);
+    assert_eq!(score.points, score.buildings + game.config.rules.most_bugs_bonus);
+    assert_eq!(score.hidden_points, Some(0));
+    assert_eq!(score.bugs, 3);
+
+    // Victory point cards stay secret from everyone else
+    assert_eq!(status.scoreboard[1].hidden_points, None);
+    let other = &status.scoreboard[1];
+    assert_eq!(other.points, other.bonuses.iter().fold(other.buildings, |acc, bonus| acc + bonus.points));
+    assert_eq!(game.get_game_status("key2").scoreboard[1].hidden_points, Some(2));
+    assert!(game.get_game_status("").scoreboard.iter().all(|score| score.hidden_points.is_none()));
+}
+
+#[test]
+fn turn_timer_plays_out_the_turn() {
+    let mut game = play_round_one(game_setup());
+    let now = SystemTime::now();
+    game.start_turn_clock(now);
+    assert_eq!(game.get_game_status("key1").turn_deadline, None);
+    assert!(!game.expire_turn(now + Duration::from_secs(3600)).unwrap());
+
+    // Everyone is told when the turn runs out, rather than how long is left
+    game.config.rules.turn_time_limit = 60;
+    let deadline = now + Duration::from_secs(60);
+    assert_eq!(
+        game.get_game_status("key1").turn_deadline,
+        Some(deadline.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64)
+    );
+    assert!(!game.expire_turn(now + Duration::from_secs(59)).unwrap());
+
+    let events = game.log.events.len();
+    assert!(game.expire_turn(now + Duration::from_secs(60)).unwrap());
+    let acted: Vec<Actions> = game.log.events[events..].iter().filter_map(
+        | event | match event {
+            Event::Acted { command, .. } => Some(command.action),
+            _ => None
+        }
+    ).collect();
+    assert_eq!(acted.first(), Some(&Actions::RollDice));
+    assert_eq!(acted.contains(&Actions::MoveScorpion), game.roll_result.0 + game.roll_result.1 == 7);
+    assert_eq!(acted.last(), Some(&Actions::EndTurn));
+    assert_eq!(game.players.active_player.as_ref().unwrap().key, "key2");
+
+    // The next player gets the whole time limit
+    assert!(!game.expire_turn(now + Duration::from_secs(119)).unwrap());
+    assert!(game.expire_turn(now + Duration::from_secs(120)).unwrap());
+}
+
+#[test]
+fn turn_timer_places_for_players_during_setup() {
+    let mut game = HexagonIsland::new();
+    game.configure_game(Config {
+        rules: Rules { turn_time_limit: 60, ..Rules::default() },
+        ..Config::default()
+    }).unwrap();
+    game.add_player("key1", "name1").unwrap()
+        .add_player("key2", "name2").unwrap();
+    let mut now = SystemTime::now();
+    game.start_turn_clock(now);
+
+    // Nobody touches anything, so the timer does the whole snake draft
+    for _ in 0..4 {
+        assert_eq!(game.phase, Phase::Setup);
+        now += Duration::from_secs(60);
+        assert!(game.expire_turn(now).unwrap());
+    }
+    assert_eq!(game.phase, Phase::Play);
+    for key in [String::from("key1"), String::from("key2")] {
+        assert_eq!(count_player_nodes(&key, &game.board.nodes), 2);
+        assert_eq!(count_player_roads(&key, &game.board.roads), 2);
+    }
+
+    // Where the timer built replays like anything else
+    let replayed = HexagonIsland::replay(game.config.clone(), &game.log).unwrap();
+    assert_eq!(replayed.board, game.board);
+}
+
+#[test]
+fn new_turns_restart_the_clock() {
+    let mut game = HexagonIsland::new();
+    game.configure_game(Config {
+        rules: Rules { turn_time_limit: 60, ..Rules::default() },
+        ..Config::default()
+    }).unwrap();
+    game.add_player("key1", "name1").unwrap()
+        .add_player("key2", "name2").unwrap();
+    let mut now = SystemTime::now();
+    for _ in 0..3 {
+        now += Duration::from_secs(60);
+        game.expire_turn(now).unwrap();
+    }
+
+    // Finishing the setup starts the clock on the first turn of play
+    game.start_turn_clock(UNIX_EPOCH);
+    let key = game.players.active_player.as_ref().unwrap().key.clone();
+    let command = game.random_setup_placement(&key).unwrap();
+    game.process_action(command).unwrap();
+    game.process_action(Command::new(Actions::EndTurn, key)).unwrap();
+    assert_eq!(game.phase, Phase::Play);
+    assert!(!game.expire_turn(SystemTime::now()).unwrap());
+
+    // And so does handing the dice to the next player
+    game.start_turn_clock(UNIX_EPOCH);
+    let key = game.players.active_player.as_ref().unwrap().key.clone();
+    game.process_action(Command::new(Actions::RollDice, key.clone())).unwrap();
+    if game.roll_result.0 + game.roll_result.1 == 7 {
+        let command = game.random_scorpion_move(&key).unwrap();
+        game.process_action(command).unwrap();
+    }
+    game.process_action(Command::new(Actions::EndTurn, key.clone())).unwrap();
+    assert_ne!(game.players.active_player.as_ref().unwrap().key, key);
+    assert!(!game.expire_turn(SystemTime::now()).unwrap());
+}
+
+#[test]
+fn turn_timer_moves_the_scorpion() {
+    let mut game = play_round_one(game_setup());
+    game.config.rules.turn_time_limit = 60;
+    let mut now = SystemTime::now();
+    game.start_turn_clock(now);
+
+    for _ in 0..20 {
+        game.last_action = Actions::RollDice;
+        game.roll_result = (3,4);
+        let scorpion = game.board.scorpion_index;
+
+        now += Duration::from_secs(60);
+        assert!(game.expire_turn(now).unwrap());
+        assert_ne!(game.board.scorpion_index, scorpion);
+        assert_eq!(game.last_action, Actions::EndTurn);
+    }
+}
+
+#[test]
+fn flag_idle_players() {
+    let mut game = play_round_one(game_setup());
+    game.config.rules.turn_time_limit = 60;
+    let mut now = SystemTime::now();
+    game.start_turn_clock(now);
+
+    // Everyone runs out of time twice
+    for missed in 0..4 {
+        if missed == 2 { assert!(game.get_game_status("key1").players.iter().all(|p| !p.idle)); }
+        now += Duration::from_secs(60);
+        assert!(game.expire_turn(now).unwrap());
+    }
+    assert!(game.get_game_status("key1").players.iter().all(|p| p.idle));
+
+    // Coming back to the table clears the flag
+    game.process_action(Command::new(Actions::RollDice, String::from("key1"))).unwrap();
+    let status = game.get_game_status("key2");
+    assert!(!status.players[0].idle);
+    assert!(status.players[1].idle);
+}
+
+#[test]
+fn bug_cards_move_the_scorpion() {
+    let mut game = play_round_one(game_setup());
+    game.last_action = Actions::RollDice;
+    game.roll_result = (3,3);
+    game.hands.insert(String::from("key1"), vec![Card::Bug, Card::RoadBuilding]);
+
+    let hex_index = (0..game.board.hexagons.len()).find(
+        | &hex | Some(hex) != game.board.scorpion_index && game.board.find_neighboring_nodes(hex).iter().all(
+            | &n | game.board.nodes[n].player_key.as_deref() != Some("key2")
+        )
+    ).unwrap();
+    let mut command = Command::new(Actions::PlayCard, String::from("key1"));
+    command.play = Some(Play::Bug);
+    assert_eq!(game.process_action(command.clone()).unwrap_err().code, ErrorCode::InvalidTarget);
+    assert_eq!(game.hands["key1"].len(), 2);
+
+    command.target[0] = Some( (Target::Hex, hex_index) );
+    game.process_action(command).unwrap();
+    assert_eq!(game.board.scorpion_index, Some(hex_index));
+    assert_eq!(game.bugs["key1"], 1);
+    assert_eq!(game.hands["key1"], vec![Card::RoadBuilding]);
+    assert_eq!(game.last_action, Actions::PlayCard);
+}
+
+#[test]
+fn rules_from_form() {
+    let config: Config = serde_urlencoded::from_str(
+        "num_players=3&score_to_win=8&game_board_width=5&seed=&discard_limit=9&bank_ratio=2&town_cost=rock+rock+cereal"
+    ).unwrap();
+    assert_eq!(config.num_players, 3);
+    assert_eq!(config.seed, None);
+    assert_eq!(config.rules.discard_limit, 9);
+    assert_eq!(config.rules.bank_ratio, 2);
+    assert_eq!(config.rules.town_cost, ResourceList { rock: 2, cereal: 1, ..ResourceList::new() });
+    assert_eq!(config.rules.road_cost, Rules::default().road_cost);
+
+    let attempt = serde_urlencoded::from_str::<Config>(
+        "num_players=2&score_to_win=10&game_board_width=5&road_cost=block+gold"
+    );
+    assert!(attempt.is_err());
+
+    // Costs can also be sent as resource lists
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5, \"card_cost\": {\"rock\": 2}}"
+    ).unwrap();
+    assert_eq!(config.rules.card_cost, ResourceList { rock: 2, ..ResourceList::new() });
+}
+
+#[test]
+fn house_rules() {
+    let mut game = HexagonIsland::new();
+    let rules = Rules { bank_ratio: 0, ..Rules::default() };
+    let attempt = game.configure_game(Config { rules, ..Config::default() });
+    assert_eq!(attempt.unwrap_err().code, ErrorCode::InvalidConfig);
+    let rules = Rules { card_cost: ResourceList::new(), ..Rules::default() };
+    let attempt = game.configure_game(Config { rules, ..Config::default() });
+    assert_eq!(attempt.unwrap_err().code, ErrorCode::InvalidConfig);
+
+    let mut game = play_round_one(game_setup());
+    game.config.rules.bank_ratio = 2;
+    game.config.rules.card_cost = ResourceList { timber: 1, ..ResourceList::new() };
+    game.last_action = Actions::RollDice;
+    game.roll_result = (3,3);
+    game.player_resources.insert(String::from("key1"), ResourceList { block: 2, ..ResourceList::new() });
+    assert_eq!(game.get_game_status("key1").rules, game.config.rules);
+
+    let mut command = Command::new(Actions::Trade, String::from("key1"));
+    command.trade = Some((Resource::Block, Resource::Timber));
+    game.process_action(command).unwrap();
+    assert_eq!(game.player_resources["key1"], ResourceList { timber: 1, ..ResourceList::new() });
+
+    game.process_action(Command::new(Actions::BuyBug, String::from("key1"))).unwrap();
+    assert_eq!(game.player_resources["key1"], ResourceList::new());
+    assert_eq!(game.hands["key1"].len(), 1);
+}
+
+#[test]
+fn generic_harbors_beat_the_bank() {
+    let mut game = play_round_one(game_setup());
+    assert_eq!(game.config.rules.bank_ratio, 3);
+    let generic = game.board.harbors.iter().find(|h| h.resource.is_none()).unwrap().road;
+    let node = game.board.roads[generic].inds.0;
+    game.board.nodes[node].player_key = Some(String::from("key1"));
+    game.last_action = Actions::RollDice;
+    game.roll_result = (3,3);
+    game.player_resources.insert(String::from("key1"), ResourceList { block: 2, ..ResourceList::new() });
+
+    let mut command = Command::new(Actions::Trade, String::from("key1"));
+    command.trade = Some((Resource::Block, Resource::Timber));
+    game.process_action(command).unwrap();
+    assert_eq!(game.player_resources["key1"], ResourceList { timber: 1, ..ResourceList::new() });
+}
+
+#[test]
+fn reject_impossible_configs() {
+    let attempts = [
+        Config { num_players: 0, ..Config::default() },
+        Config { num_players: PLAYER_COLORS.len() + 1, ..Config::default() },
+        Config { score_to_win: 0, ..Config::default() },
+        Config { game_board_width: 0, ..Config::default() },
+        Config { game_board_width: 1, ..Config::default() },
+        Config { game_board_width: 4, ..Config::default() },
+        Config { game_board_width: 17, ..Config::default() },
+        Config { num_players: 4, game_board_width: 3, ..Config::default() },
+        Config { rules: Rules { bank_ratio: 0, ..Rules::default() }, ..Config::default() }
+    ];
+    for config in attempts {
+        let mut game = HexagonIsland::new();
+        let attempt = game.configure_game(config.clone());
+        assert_eq!(attempt.unwrap_err().code, ErrorCode::InvalidConfig, "{:?}", config);
+    }
+
+    assert!(Config { num_players: PLAYER_COLORS.len(), game_board_width: 9, ..Config::default() }.validate().is_ok());
+    assert!(Config { num_players: 3, game_board_width: 3, ..Config::default() }.validate().is_ok());
+
+    // Balanced numbers are impossible once the map itself puts a 6 next to an 8
+    let map: BoardMap = serde_json::from_str(r#"{
+        "tiles": [
+            {"q": 0, "r": 0, "resource": "Rock", "number": 6},
+            {"q": 1, "r": 0, "resource": "Timber", "number": 8},
+            {"q": 0, "r": 1},
+            {"q": 1, "r": 1}
+        ]
+    }"#).unwrap();
+    let config = Config { map: Some(map), number_placement: NumberPlacement::Balanced, ..Config::default() };
+    assert_eq!(config.validate().unwrap_err().code, ErrorCode::InvalidConfig);
+    assert!(Config { number_placement: NumberPlacement::Random, ..config }.validate().is_ok());
+}
+
+#[test]
+fn balanced_games_use_the_board_they_were_checked_with() {
+    let mut game = HexagonIsland::new();
+    let config = Config { num_players: 2, number_placement: NumberPlacement::Balanced, ..Config::default() };
+    game.configure_game(config).unwrap();
+    let seed = game.config.seed.unwrap();
+
+    game.add_player("key1", "name1").unwrap()
+        .add_player("key2", "name2").unwrap();
+    let mut board = GameBoard::new();
+    board.setup(&game.config.get_map(), NumberPlacement::Balanced, &mut GameRng::seed_from_u64(seed)).unwrap();
+    assert_eq!(game.config.seed, Some(seed));
+    assert_eq!(game.board.hexagons, board.hexagons);
+}
+
+#[test]
+fn maps_from_config() {
+    let config: Config = serde_urlencoded::from_str(
+        "num_players=4&score_to_win=10&game_board_width=5&map=rectangle&number_placement=Spiral"
+    ).unwrap();
+    assert_eq!(config.get_map(), BoardMap::builtin("rectangle").unwrap());
+    assert_eq!(config.number_placement, NumberPlacement::Spiral);
+    assert_eq!(config.validate(), Ok(()));
+
+    let config: Config = serde_urlencoded::from_str("num_players=2&score_to_win=10&game_board_width=7&map=").unwrap();
+    assert_eq!(config.map, None);
+    assert_eq!(config.get_map(), BoardMap::hexagon(7));
+    assert_eq!(config.number_placement, NumberPlacement::Random);
+
+    let attempt = serde_urlencoded::from_str::<Config>("num_players=2&score_to_win=10&game_board_width=5&map=atlantis");
+    assert!(attempt.is_err());
+
+    // A map spelled out in full, which is also how a history keeps it
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 1, \"score_to_win\": 10, \"game_board_width\": 0, \"map\": {\"tiles\": [{\"q\": 0, \"r\": 0}, {\"q\": 1, \"r\": 0}]}}"
+    ).unwrap();
+    assert_eq!(config.validate(), Ok(()));
+    assert_eq!(serde_json::from_str::<Config>(&serde_json::to_string(&config).unwrap()).unwrap(), config);
+    let too_small = Config { num_players: 2, ..config.clone() };
+    assert_eq!(too_small.validate().unwrap_err().code, ErrorCode::InvalidConfig);
+
+    let mut game = HexagonIsland::new();
+    game.configure_game(Config { map: BoardMap::builtin("archipelago"), ..Config::default() }).unwrap();
+    game.add_player("key1", "name1").unwrap()
+        .add_player("key2", "name2").unwrap();
+    assert_eq!(game.board.hexagons.len(), 19);
+    assert_eq!(game.board.scorpion_index, Some(3));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    // Whatever makes it past validation has to be playable without panicking
+    #[test]
+    fn accepted_configs_set_up(
+        num_players in 0..=30usize,
+        score_to_win in 0..=20u8,
+        game_board_width in 0..=20u8,
+        seed in any::<u64>()
+    ) {
+        let config = Config {
+            num_players,
+            score_to_win,
+            game_board_width,
+            seed: Some(seed),
+            map: None,
+            number_placement: NumberPlacement::Random,
+            rules: Rules::default()
+        };
+        let mut game = HexagonIsland::new();
+        prop_assume!(game.configure_game(config).is_ok());
+
+        for idx in 0..num_players {
+            game.add_player(&format!("key{}", idx), &format!("name{}", idx)).unwrap();
+        }
+        prop_assert_eq!(game.phase, Phase::Setup);
+        prop_assert!(game.board.hexagons.len() >= 2 * num_players);
+        prop_assert!(game.board.scorpion_index.is_some());
+    }
+
+    #[test]
+    fn any_map_sets_up(
+        positions in proptest::collection::hash_set((-6..=6i16, -6..=6i16), 1..40),
+        seed in any::<u64>()
+    ) {
+        let tiles = positions.into_iter().map(|(q, r)| MapTile { q, r, resource: None, number: None }).collect();
+        let map = BoardMap { name: String::new(), description: String::new(), tiles };
+        prop_assert_eq!(map.validate(), Ok(()));
+
+        let mut board = GameBoard::new();
+        board.setup(&map, NumberPlacement::Random, &mut GameRng::seed_from_u64(seed)).unwrap();
+        prop_assert_eq!(board.hexagons.len(), map.tiles.len());
+        prop_assert_eq!(board.hexagons.iter().filter(|h| h.resource == Resource::Desert).count(), 1);
+        prop_assert!(board.hexagons.iter().all(|h| (h.resource == Resource::Desert) == (h.number == 1)));
+    }
+
+    #[test]
+    fn any_odd_width_sets_up(half_width in 1..=7u8, seed in any::<u64>()) {
+        let mut board = GameBoard::new();
+        board.setup(&BoardMap::hexagon(2 * half_width + 1), NumberPlacement::Random, &mut GameRng::seed_from_u64(seed)).unwrap();
+        prop_assert!(!board.hexagons.is_empty());
+    }
+}
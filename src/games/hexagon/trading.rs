@@ -0,0 +1,70 @@
+use serde::{Serialize, Deserialize};
+
+use super::resources::ResourceList;
+
+/// One side of a trade: hand over `give` and receive `get` in return
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub give: ResourceList,
+    pub get: ResourceList
+}
+
+/// A proposal waiting for an answer; anyone but `from` may take it up when `to` is empty
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Offer {
+    pub id: usize,
+    pub from: String,
+    pub to: Option<String>,
+    pub bundle: Bundle,
+    pub declined: Vec<String>
+}
+
+impl Offer {
+    pub fn can_answer(&self, key: &str) -> bool {
+        match &self.to {
+            Some(to) => to == key,
+            None => self.from != key && !self.declined.iter().any(|d| d == key)
+        }
+    }
+}
+
+/// Every offer still on the table this turn
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Offers {
+    next_id: usize,
+    pub list: Vec<Offer>
+}
+
+impl Offers {
+    pub fn new() -> Offers {
+        Offers { next_id: 0, list: Vec::new() }
+    }
+
+    pub fn propose(&mut self, from: &str, to: Option<String>, bundle: Bundle) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.list.push(Offer { id, from: String::from(from), to, bundle, declined: Vec::new() });
+
+        id
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Offer> {
+        self.list.iter().find(|o| o.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Offer> {
+        self.list.iter_mut().find(|o| o.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Offer> {
+        let idx = self.list.iter().position(|o| o.id == id)?;
+
+        Some(self.list.remove(idx))
+    }
+
+    pub fn clear(&mut self) -> &mut Offers {
+        self.list.truncate(0);
+
+        self
+    }
+}
@@ -1,2 +1,2 @@
 pub mod core;
-pub mod hexagon;
\ No newline at end of file
+pub mod hexagon;
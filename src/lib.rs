@@ -0,0 +1,4 @@
+pub mod games;
+pub mod protocol;
+pub mod rooms;
+pub mod store;
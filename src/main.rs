@@ -2,42 +2,41 @@ use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
         Extension,
+        Path,
+        Query
     },
     response::{Html, IntoResponse},
     http::StatusCode,
-    routing::{get, post},
+    routing::{get, post, delete},
     Router,
-    Form
+    Form,
+    Json
 };
 use futures::{sink::SinkExt, stream::StreamExt};
-use serde::Deserialize;
 use std::{
     net::SocketAddr,
-    sync::{Arc, Mutex},
+    sync::Arc,
+    time::Duration,
 };
-use tokio::sync::broadcast;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use serde_json::from_str;
-use rand::{thread_rng, Rng};
-use rand::distributions::Alphanumeric;
-
-mod games;
-use crate::games::core::traits::Game;
-use games::hexagon::HexagonIsland;
-use games::hexagon::actions::Command;
-use games::hexagon::Config;
+use serde::Deserialize;
+use serde_json::json;
 
-#[derive(Clone)]
-enum BroadcastType {
-    Status,
-    Error {player_key: String, message: String}
-}
+use game_serve_rs::games::core::error::{GameError, ErrorCode};
+use game_serve_rs::games::hexagon::Config;
+use game_serve_rs::protocol::{self, encode, ClientMessage, ServerMessage, StatusFeed};
+use game_serve_rs::rooms::{BroadcastType, History, Room, Rooms};
+use game_serve_rs::store::FileStore;
 
 // Our shared state
 struct AppState {
-    producer: broadcast::Sender<BroadcastType>,
-    game: Mutex<HexagonIsland>
+    rooms: Rooms
+}
+
+#[derive(Deserialize)]
+struct Seat {
+    key: String
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -49,24 +48,32 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let (producer, _listener) = broadcast::channel(100);
-    let game = Mutex::new(HexagonIsland::new());
+    // Reload any games that were in progress before the last shutdown
+    let snapshot_dir = std::env::var("SNAPSHOT_DIR").unwrap_or_else(|_| "snapshots".into());
+    let store = FileStore::new(snapshot_dir).unwrap();
+    let rooms = Rooms::restore(Arc::new(store)).unwrap();
 
-    let app_state = Arc::new(AppState { producer, game });
+    let app_state = Arc::new(AppState { rooms });
 
-    // Broadcast the game state at regular intervals
-    let cloned_app_state = app_state.clone();
+    // Look for anyone who has run out of time on their turn once a second
+    let timer_state = app_state.clone();
     tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
         loop {
-            let _ = cloned_app_state.producer.send(BroadcastType::Status);
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            interval.tick().await;
+            timer_state.rooms.expire_turns();
         }
     });
 
     let app = Router::new()
         .route("/", get(index))
-        .route("/start", post(start_game))
-        .route("/websocket", get(websocket_handler))
+        .route("/rooms", get(list_rooms).post(create_room))
+        .route("/rooms/:room_id", delete(delete_room))
+        .route("/rooms/:room_id/start", post(start_game))
+        .route("/rooms/:room_id/board", get(board_layout))
+        .route("/rooms/:room_id/history", get(room_history))
+        .route("/replays", post(replay_room))
+        .route("/rooms/:room_id/websocket", get(websocket_handler))
         .layer(Extension(app_state)); // injecting state into all the above routes
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
@@ -79,19 +86,17 @@ async fn main() {
 
 async fn websocket_handler(
     ws: WebSocketUpgrade,
+    Path(room_id): Path<String>,
     Extension(state): Extension<Arc<AppState>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| websocket(socket, state))
-}
-
-#[derive(Deserialize)]
-struct Joining {
-    name: String,
-    key: Option<String>
+    match state.rooms.get(&room_id) {
+        Some(room) => ws.on_upgrade(|socket| websocket(socket, room)),
+        None => (StatusCode::NOT_FOUND, "Room not found").into_response()
+    }
 }
 
 // on upgrade to ws
-async fn websocket(stream: WebSocket, state: Arc<AppState>) {
+async fn websocket(stream: WebSocket, room: Arc<Room>) {
     // By splitting we can send and receive at the same time.
     let (mut ws_tx, mut ws_rx) = stream.split();
 
@@ -101,93 +106,82 @@ async fn websocket(stream: WebSocket, state: Arc<AppState>) {
     // Loop until an initial message is found.
     while let Some(Ok(message)) = ws_rx.next().await {
         if let Message::Text(text) = message {
-            match from_str::<Joining>(&text) {
-                Ok(joined) => {
-                    let Joining {name, key: cached_key} = joined;
-                    match cached_key {
-                        Some(ckey) => {
-                            let game = state.game.lock().unwrap();
-                            if game.players.list.iter().any(|p| p.key == ckey) {
-                                key = ckey;
-                                break;
-                            }
-                        },
-                        None => {}
+            let error = match protocol::decode(&text) {
+                Ok(ClientMessage::Join {name, key: cached_key}) => {
+                    if let Some(ckey) = cached_key {
+                        if room.has_player(&ckey) {
+                            key = ckey;
+                            break;
+                        }
                     }
                     // Try to add this player to game.
-                    let attempt = add_player(&state, &name);
-                    match attempt {
+                    match room.add_player(&name) {
                         Ok(val) => {
                             key = val;
                             break;
                         },
-                        Err(msg) => {
-                            let _ = ws_tx.send(
-                                Message::Text(
-                                    String::new() +
-                                    "{" +
-                                    "\"error\": " +
-                                    "\"" + msg + "\"" +
-                                    "}"
-                                )
-                            ).await;
+                        Err(error) => {
+                            let _ = ws_tx.send(Message::Text(encode(ServerMessage::Error(error)))).await;
                             return;
                         }
                     }
                 },
-                Err(err) => { // Deserialization error
-                    let _ = state.producer.send(BroadcastType::Error { 
-                        player_key: key.clone(),
-                        message: err.to_string()
-                    });
-                }
-
+                Ok(_) => GameError::new(ErrorCode::UnexpectedMessage, "Join the game before sending anything else."),
+                Err(error) => error
+            };
+            if ws_tx.send(Message::Text(encode(ServerMessage::Error(error)))).await.is_err() {
+                return;
             }
-            
         }
     }
 
-    // Subscribe this task to the broadcast channel.
-    let mut listener = state.producer.subscribe();
+    // Subscribe this task to the room's broadcast channel.
+    let mut listener = room.producer.subscribe();
 
     // Need to make clones that the transmit task will take ownership of.
-    let cloned_app_state = state.clone();
+    let cloned_room = room.clone();
     let cloned_key = key.clone();
 
     // This task will receive broadcast messages and send text message to our client.
     let mut websocket_transmit_task = tokio::spawn(async move {
+        // Start this client off with everything, after that it only gets what changed
+        let mut feed = StatusFeed::new();
+        let snapshot = feed.snapshot(cloned_room.game_status(&cloned_key));
+        if ws_tx.send(Message::Text(encode(snapshot))).await.is_err() {
+            return;
+        }
+
         while let Ok(broadcast) = listener.recv().await {
             match broadcast {
                 BroadcastType::Status => {
-                    let serialized = serialize_game_status(&cloned_app_state, &cloned_key);
-                    if ws_tx.send(
-                        Message::Text(
-                            String::new() +
-                            "{" +
-                            "\"state\": " +
-                            &serialized +
-                            "}"
-                        )
+                    if let Some(delta) = feed.update(cloned_room.game_status(&cloned_key)) {
+                        if ws_tx.send(Message::Text(encode(delta))).await.is_err() {
+                            // break loop on any websocket error
+                            break;
+                        }
+                    }
+                },
+                BroadcastType::Error {player_key,error} => {
+                    if cloned_key == player_key && ws_tx.send(
+                        Message::Text(encode(ServerMessage::Error(error)))
                     ).await.is_err() {
                         // break loop on any websocket error
                         break;
                     }
                 },
-                BroadcastType::Error {player_key,message} => {
-                    if *&cloned_key == player_key { 
-                        if ws_tx.send(
-                            Message::Text(
-                                String::new() +
-                                "{" +
-                                "\"error\": " +
-                                "\"" + &message + "\"" +
-                                "}"
-                            )
-                        ).await.is_err() {
+                BroadcastType::Resync {player_key} => {
+                    if cloned_key == player_key {
+                        let snapshot = feed.snapshot(cloned_room.game_status(&cloned_key));
+                        if ws_tx.send(Message::Text(encode(snapshot))).await.is_err() {
                             // break loop on any websocket error
                             break;
                         }
                     }
+                },
+                BroadcastType::Closed => {
+                    // The room has been deleted
+                    let _ = ws_tx.send(Message::Close(None)).await;
+                    break;
                 }
             }
         }
@@ -196,26 +190,22 @@ async fn websocket(stream: WebSocket, state: Arc<AppState>) {
     // This task will receive messages from client and send them to broadcast subscribers.
     let mut websocket_receive_task = tokio::spawn(async move {
         while let Some(Ok(Message::Text(text))) = ws_rx.next().await {
-            // Try to deserialize text into a Command struct
-            match from_str::<Command>(&text) {
-                Ok(cmd) => { // Deserialized Command
-                    let attempt = process_command(&state, cmd);
-                    match attempt {
-                        Ok(_) => {},
-                        Err(msg) => {
-                            let _ = state.producer.send(BroadcastType::Error { 
-                                player_key: key.clone(),
-                                message: msg.to_string() 
-                            });
-                        }
-                    }
+            let attempt = match protocol::decode(&text) {
+                Ok(ClientMessage::Command(cmd)) => room.process_command(&key, cmd),
+                Ok(ClientMessage::Resync) => {
+                    let _ = room.producer.send(BroadcastType::Resync { player_key: key.clone() });
+                    Ok(())
                 },
-                Err(err) => { // Deserialization error
-                    let _ = state.producer.send(BroadcastType::Error { 
-                        player_key: key.clone(),
-                        message: err.to_string() //"Error: Malformed command".to_string() //err.to_string() 
-                    });
-                }
+                Ok(ClientMessage::Join {..}) => Err(
+                    GameError::new(ErrorCode::UnexpectedMessage, "You have already joined this game.")
+                ),
+                Err(error) => Err(error)
+            };
+            if let Err(error) = attempt {
+                let _ = room.producer.send(BroadcastType::Error {
+                    player_key: key.clone(),
+                    error
+                });
             }
         }
     });
@@ -231,49 +221,75 @@ async fn websocket(stream: WebSocket, state: Arc<AppState>) {
 
 }
 
-fn add_player(state: &AppState, name: &str) -> Result<String,&'static str> {
-    let key: String = thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(16)
-        .map(char::from)
-        .collect();
+// Include utf-8 file at **compile** time.
+async fn index() -> Html<&'static str> {
+    Html(std::include_str!("../assets/index.html"))
+}
 
-    let mut game = state.game.lock().unwrap();
-    let result = game.add_player(&key, name);
-    match result {
-        Ok(_) => Ok(key),
-        Err(msg) => Err(msg)
+async fn list_rooms(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.rooms.summaries())
+}
+
+async fn create_room(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    let room_id = state.rooms.create();
+    (StatusCode::CREATED, Json(json!({ "id": room_id })))
+}
+
+async fn delete_room(Path(room_id): Path<String>, Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    match state.rooms.remove(&room_id) {
+        Some(_) => (StatusCode::NO_CONTENT, ""),
+        None => (StatusCode::NOT_FOUND, "Room not found")
     }
 }
 
-fn serialize_game_status(state: &AppState, key: &str) -> String {
-    let game = state.game.lock().unwrap();
-    game.get_game_status(key)
+async fn board_layout(Path(room_id): Path<String>, Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    match state.rooms.get(&room_id) {
+        Some(room) => Json(room.board_layout()).into_response(),
+        None => (StatusCode::NOT_FOUND, "Room not found").into_response()
+    }
 }
 
-fn process_command(state: &AppState, cmd: Command) -> Result<(),&'static str> {
-    let mut game = state.game.lock().unwrap();
-    let result = game.process_action(cmd);
-    match result {
-        Ok(_) => Ok(()),
-        Err(msg) => Err(msg)
+// Only someone seated in the room can ask for its history, by passing their key
+async fn room_history(
+    Path(room_id): Path<String>,
+    Query(seat): Query<Seat>,
+    Extension(state): Extension<Arc<AppState>>
+) -> impl IntoResponse {
+    let room = match state.rooms.get(&room_id) {
+        Some(room) => room,
+        None => return (StatusCode::NOT_FOUND, "Room not found").into_response()
+    };
+
+    match room.history(&seat.key) {
+        Ok(history) => Json(history).into_response(),
+        Err(error) => (StatusCode::FORBIDDEN, Json(error)).into_response()
     }
 }
 
-// Include utf-8 file at **compile** time.
-async fn index() -> Html<&'static str> {
-    Html(std::include_str!("../assets/index.html"))
+async fn replay_room(Extension(state): Extension<Arc<AppState>>, Json(history): Json<History>) -> impl IntoResponse {
+    match state.rooms.replay(history) {
+        Ok(room_id) => (StatusCode::CREATED, Json(json!({ "id": room_id }))).into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, Json(error)).into_response()
+    }
 }
 
-async fn start_game(form: Form<Config>, Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
-    // Use Form extractor to get configuration that was posted 
+async fn start_game(
+    Path(room_id): Path<String>,
+    Extension(state): Extension<Arc<AppState>>,
+    form: Form<Config>
+) -> impl IntoResponse {
+    let room = match state.rooms.get(&room_id) {
+        Some(room) => room,
+        None => return (StatusCode::NOT_FOUND, "Room not found").into_response()
+    };
+
+    // Use Form extractor to get configuration that was posted
     // as application/x-www-form-urlencoded
     let config = form.0;
-    let mut game = state.game.lock().unwrap();
-    let result = game.reset().configure_game(config);
+    let result = room.start_game(config);
 
     match result {
-        Ok(_) => (StatusCode::CREATED, "Game started"),
-        Err(msg) => (StatusCode::BAD_REQUEST, msg)
+        Ok(_) => (StatusCode::CREATED, "Game started").into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, Json(error)).into_response()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,42 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+/// A single value that has to be swapped in, addressed by a JSON pointer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub path: String,
+    pub value: Value
+}
+
+/// Lists the smallest set of replacements that turn `old` into `new`
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_into(String::new(), old, new, &mut changes);
+
+    changes
+}
+
+fn diff_into(path: String, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        // Only walk into containers that kept their shape; anything else is replaced whole
+        (Value::Object(old_map), Value::Object(new_map))
+            if old_map.len() == new_map.len() && old_map.keys().all(|k| new_map.contains_key(k)) => {
+            for (key, new_value) in new_map {
+                diff_into(path.clone() + "/" + &escape(key), &old_map[key], new_value, changes);
+            }
+        },
+        (Value::Array(old_list), Value::Array(new_list)) if old_list.len() == new_list.len() => {
+            for (idx, (old_value, new_value)) in old_list.iter().zip(new_list).enumerate() {
+                diff_into(path.clone() + "/" + &idx.to_string(), old_value, new_value, changes);
+            }
+        },
+        _ => if old != new {
+            changes.push(Change { path, value: new.clone() });
+        }
+    }
+}
+
+// Keys are escaped the way RFC 6901 asks so they can't be mistaken for separators
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,100 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{from_str, from_value, to_string, to_value, Value};
+
+use crate::games::core::error::{ GameError, ErrorCode };
+use crate::games::hexagon::Status;
+use crate::games::hexagon::actions::Command;
+
+mod diff;
+pub use diff::{ Change, diff };
+
+/// Bumped whenever a message changes shape
+pub const PROTOCOL_VERSION: u16 = 12;
+
+/// Every message on the websocket is wrapped in one of these
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u16,
+    pub message: T
+}
+
+/// Messages a client sends to the server
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join { name: String, key: Option<String> },
+    Command(Command),
+    Resync
+}
+
+/// Messages the server sends to a client
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Snapshot { seq: u64, status: Box<Status> },
+    Delta { seq: u64, changes: Vec<Change> },
+    Error(GameError)
+}
+
+/// Remembers what one client was last sent so that only the changes go out next time
+#[derive(Default)]
+pub struct StatusFeed {
+    seq: u64,
+    last: Option<Value>
+}
+
+impl StatusFeed {
+    pub fn new() -> StatusFeed {
+        StatusFeed { seq: 0, last: None }
+    }
+
+    /// The full status, for new clients and for clients that lost track
+    pub fn snapshot(&mut self, status: Status) -> ServerMessage {
+        self.seq += 1;
+        self.last = Some(to_value(&status).unwrap());
+
+        ServerMessage::Snapshot { seq: self.seq, status: Box::new(status) }
+    }
+
+    /// Just what changed since the last message, or nothing if nothing did
+    pub fn update(&mut self, status: Status) -> Option<ServerMessage> {
+        let last = match &self.last {
+            Some(last) => last,
+            None => return Some(self.snapshot(status))
+        };
+
+        let next = to_value(&status).unwrap();
+        let changes = diff(last, &next);
+        if changes.is_empty() { return None; }
+
+        self.seq += 1;
+        self.last = Some(next);
+
+        Some(ServerMessage::Delta { seq: self.seq, changes })
+    }
+}
+
+pub fn encode(message: ServerMessage) -> String {
+    to_string(&Envelope { version: PROTOCOL_VERSION, message }).unwrap()
+}
+
+pub fn decode(text: &str) -> Result<ClientMessage, GameError> {
+    // Check the version before the message so old clients get told why they were turned down
+    let envelope = from_str::<Envelope<Value>>(text).map_err(
+        |err| GameError::new(ErrorCode::MalformedMessage, &err.to_string())
+    )?;
+
+    if envelope.version != PROTOCOL_VERSION {
+        return Err(GameError::new(
+            ErrorCode::UnsupportedVersion,
+            &format!("Protocol version {} is not supported; expected {}.", envelope.version, PROTOCOL_VERSION)
+        ));
+    }
+
+    from_value::<ClientMessage>(envelope.message).map_err(
+        |err| GameError::new(ErrorCode::MalformedMessage, &err.to_string())
+    )
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,119 @@
+use super::*;
+use serde_json::json;
+use crate::games::core::traits::Game;
+use crate::games::hexagon::HexagonIsland;
+use crate::games::hexagon::actions::Actions;
+
+#[test]
+fn decode_client_messages() {
+    let text = json!({
+        "version": PROTOCOL_VERSION,
+        "message": { "type": "join", "name": "name \"with\" quotes", "key": null }
+    }).to_string();
+    assert_eq!(
+        decode(&text),
+        Ok(ClientMessage::Join { name: String::from("name \"with\" quotes"), key: None })
+    );
+
+    let command = Command::new(Actions::RollDice, String::from("key1"));
+    let text = to_string(&Envelope { version: PROTOCOL_VERSION, message: ClientMessage::Command(command.clone()) }).unwrap();
+    assert_eq!(decode(&text), Ok(ClientMessage::Command(command)));
+}
+
+#[test]
+fn reject_bad_messages() {
+    let text = json!({
+        "version": PROTOCOL_VERSION + 1,
+        "message": { "type": "join", "name": "name1", "key": null }
+    }).to_string();
+    assert_eq!(decode(&text).unwrap_err().code, ErrorCode::UnsupportedVersion);
+
+    let text = json!({ "name": "name1", "key": null }).to_string();
+    assert_eq!(decode(&text).unwrap_err().code, ErrorCode::MalformedMessage);
+
+    let text = json!({
+        "version": PROTOCOL_VERSION,
+        "message": { "type": "dance" }
+    }).to_string();
+    assert_eq!(decode(&text).unwrap_err().code, ErrorCode::MalformedMessage);
+}
+
+#[test]
+fn encode_server_messages() {
+    let error = GameError::new(ErrorCode::PlayerNotFound, "No \"player\" here.");
+    let encoded = from_str::<Value>(&encode(ServerMessage::Error(error))).unwrap();
+    assert_eq!(
+        encoded,
+        json!({
+            "version": PROTOCOL_VERSION,
+            "message": { "type": "error", "code": "player_not_found", "message": "No \"player\" here." }
+        })
+    );
+
+    let mut game = HexagonIsland::new();
+    game.add_player("key1", "name \"with\" quotes").unwrap();
+    let key = String::from("key1");
+    let snapshot = StatusFeed::new().snapshot(game.get_game_status(&key));
+    let encoded = from_str::<Value>(&encode(snapshot)).unwrap();
+    assert_eq!(encoded["message"]["type"], "snapshot");
+    assert_eq!(encoded["message"]["seq"], 1);
+    assert_eq!(encoded["message"]["status"]["key"], key.as_str());
+    assert_eq!(encoded["message"]["status"]["players"][0]["name"], "name \"with\" quotes");
+}
+
+#[test]
+fn diff_values() {
+    let old = json!({ "phase": "Boot", "roads": [{ "player_key": null }, { "player_key": null }], "colors": { "a/b": "#fff" } });
+
+    assert_eq!(diff(&old, &old), Vec::new());
+
+    let new = json!({ "phase": "Play", "roads": [{ "player_key": null }, { "player_key": "key1" }], "colors": { "a/b": "#000" } });
+    assert_eq!(
+        diff(&old, &new),
+        vec![
+            Change { path: String::from("/colors/a~1b"), value: json!("#000") },
+            Change { path: String::from("/phase"), value: json!("Play") },
+            Change { path: String::from("/roads/1/player_key"), value: json!("key1") }
+        ]
+    );
+
+    // Containers that grow or shrink are replaced whole
+    let new = json!({ "phase": "Boot", "roads": [], "colors": {} });
+    assert_eq!(
+        diff(&old, &new),
+        vec![
+            Change { path: String::from("/colors"), value: json!({}) },
+            Change { path: String::from("/roads"), value: json!([]) }
+        ]
+    );
+}
+
+#[test]
+fn feed_sends_deltas() {
+    let mut game = HexagonIsland::new();
+    let mut feed = StatusFeed::new();
+    assert!(matches!(feed.update(game.get_game_status("key1")), Some(ServerMessage::Snapshot { seq: 1, .. })));
+    assert_eq!(feed.update(game.get_game_status("key1")), None);
+
+    game.add_player("key1", "name1").unwrap();
+    game.add_player("key2", "name2").unwrap();
+    let changes = match feed.update(game.get_game_status("key1")) {
+        Some(ServerMessage::Delta { seq: 2, changes }) => changes,
+        other => panic!("expected a delta, got {:?}", other)
+    };
+    assert!(changes.iter().any(|c| c.path == "/phase" && c.value == json!("Setup")));
+
+    // Applying the delta to the last snapshot gets the client to the current status
+    let mut fresh = StatusFeed::new();
+    let mut state = json!(match fresh.snapshot(HexagonIsland::new().get_game_status("key1")) {
+        ServerMessage::Snapshot { status, .. } => status,
+        _ => unreachable!()
+    });
+    for change in changes {
+        *state.pointer_mut(&change.path).unwrap() = change.value;
+    }
+    assert_eq!(state, json!(game.get_game_status("key1")));
+
+    assert!(matches!(feed.snapshot(game.get_game_status("key1")), ServerMessage::Snapshot { seq: 3, .. }));
+}
+
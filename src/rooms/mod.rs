@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+use serde::{Serialize, Deserialize};
+use tokio::sync::broadcast;
+
+use crate::games::core::Phase;
+use crate::games::core::traits::Game;
+use crate::games::core::error::{GameError, ErrorCode};
+use crate::games::hexagon::HexagonIsland;
+use crate::games::hexagon::actions::Command;
+use crate::games::hexagon::{Config, ActionLog, BoardLayout, Status};
+use crate::store::SnapshotStore;
+
+#[derive(Clone, Debug)]
+pub enum BroadcastType {
+    Status,
+    Error {player_key: String, error: GameError},
+    Resync {player_key: String},
+    Closed
+}
+
+// A single game table with its own broadcast channel
+pub struct Room {
+    pub id: String,
+    pub producer: broadcast::Sender<BroadcastType>,
+    pub game: Mutex<HexagonIsland>,
+    store: Arc<dyn SnapshotStore>
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RoomSummary {
+    pub id: String,
+    pub phase: Phase,
+    pub players: Vec<String>
+}
+
+// Everything needed to replay a room's game somewhere else
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    pub config: Config,
+    pub log: ActionLog
+}
+
+// Registry of all the rooms being served, keyed by room ID
+pub struct Rooms {
+    list: Mutex<HashMap<String, Arc<Room>>>,
+    store: Arc<dyn SnapshotStore>
+}
+
+pub fn generate_key(length: usize) -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+impl Room {
+    pub fn new(id: &str, game: HexagonIsland, store: Arc<dyn SnapshotStore>) -> Room {
+        let (producer, _listener) = broadcast::channel(100);
+        Room {
+            id: String::from(id),
+            producer,
+            game: Mutex::new(game),
+            store
+        }
+    }
+
+    // A failed snapshot shouldn't stop the game, so just complain about it
+    fn save(&self, game: &HexagonIsland) {
+        if let Err(err) = self.store.save(&self.id, game) {
+            tracing::error!("failed to save room {}: {}", self.id, err);
+        }
+    }
+
+    // Keep the snapshot current and push the new state to everyone in the room
+    fn changed(&self, game: &HexagonIsland) {
+        self.save(game);
+        let _ = self.producer.send(BroadcastType::Status);
+    }
+
+    pub fn summary(&self) -> RoomSummary {
+        let game = self.game.lock().unwrap();
+        RoomSummary {
+            id: self.id.clone(),
+            phase: game.get_phase().clone(),
+            players: game.players.list.iter().map(|p| p.name.clone()).collect()
+        }
+    }
+
+    /// The room's history for one of its players, with everyone's key swapped for their seat
+    pub fn history(&self, key: &str) -> Result<History,GameError> {
+        let game = self.game.lock().unwrap();
+        if !game.players.list.iter().any(|p| p.key == key) {
+            return Err(GameError::new(ErrorCode::Unauthorized, "Only players in this room can see its history."));
+        }
+
+        let mut history = History {
+            config: game.get_config().clone(),
+            log: game.get_log().rename_players(|seat| format!("seat{}", seat + 1))
+        };
+        // The seed gives away every roll and card still to come, so it waits for the end like it does in the status
+        if *game.get_phase() != Phase::End {
+            history.config = history.config.without_seed();
+            history.log.board_seed = None;
+        }
+
+        Ok(history)
+    }
+
+    pub fn has_player(&self, key: &str) -> bool {
+        let game = self.game.lock().unwrap();
+        game.players.list.iter().any(|p| p.key == key)
+    }
+
+    pub fn add_player(&self, name: &str) -> Result<String,GameError> {
+        let key = generate_key(16);
+
+        let mut game = self.game.lock().unwrap();
+        game.add_player(&key, name)?;
+        self.changed(&game);
+
+        Ok(key)
+    }
+
+    pub fn board_layout(&self) -> BoardLayout {
+        let game = self.game.lock().unwrap();
+        game.get_layout()
+    }
+
+    pub fn game_status(&self, key: &str) -> Status {
+        let game = self.game.lock().unwrap();
+        game.get_game_status(key)
+    }
+
+    /// Runs a command on behalf of whoever the connection authenticated as
+    pub fn process_command(&self, key: &str, mut cmd: Command) -> Result<(),GameError> {
+        // The player field is only a hint from the client; the connection decides who is acting
+        if cmd.player.is_empty() {
+            cmd.player = String::from(key);
+        } else if cmd.player != key {
+            return Err(GameError::new(ErrorCode::Unauthorized, "You can only act as yourself."));
+        }
+
+        let mut game = self.game.lock().unwrap();
+        game.process_action(cmd)?;
+        self.changed(&game);
+
+        Ok(())
+    }
+
+    /// Plays out the active player's turn for them if they have run out of time
+    pub fn expire_turn(&self) {
+        let mut game = self.game.lock().unwrap();
+        match game.expire_turn(SystemTime::now()) {
+            Ok(true) => self.changed(&game),
+            Ok(false) => {},
+            Err(err) => tracing::error!("failed to play out a turn in room {}: {}", self.id, err)
+        }
+    }
+
+    pub fn start_game(&self, config: Config) -> Result<(),GameError> {
+        // Check first so a bad config doesn't throw away the game that is already there
+        let config = config.with_seed();
+        config.validate()?;
+        let mut game = self.game.lock().unwrap();
+        game.reset().configure_game(config)?;
+        self.changed(&game);
+
+        Ok(())
+    }
+}
+
+impl Rooms {
+    pub fn new(store: Arc<dyn SnapshotStore>) -> Rooms {
+        Rooms { list: Mutex::new(HashMap::new()), store }
+    }
+
+    /// Brings back every room that was saved in the snapshot store
+    pub fn restore(store: Arc<dyn SnapshotStore>) -> std::io::Result<Rooms> {
+        let rooms = Rooms::new(store);
+        {
+            let mut list = rooms.list.lock().unwrap();
+            for (id, mut game) in rooms.store.load_all()? {
+                // Nobody could play while the server was down, so whoever's turn it was starts over
+                game.start_turn_clock(SystemTime::now());
+                list.insert(id.clone(), Arc::new(Room::new(&id, game, rooms.store.clone())));
+            }
+        }
+
+        Ok(rooms)
+    }
+
+    /// Creates an empty room and returns its ID
+    pub fn create(&self) -> String {
+        self.insert(HexagonIsland::new())
+    }
+
+    /// Creates a room holding a game rebuilt from someone else's history
+    pub fn replay(&self, history: History) -> Result<String,GameError> {
+        // Seats get new keys, so a shared history never hands out a way into the original room
+        let log = history.log.rename_players(|_| generate_key(16));
+        let game = HexagonIsland::replay(history.config, &log)?;
+
+        Ok(self.insert(game))
+    }
+
+    fn insert(&self, game: HexagonIsland) -> String {
+        let mut list = self.list.lock().unwrap();
+        let mut id = generate_key(6);
+        while list.contains_key(&id) { id = generate_key(6); }
+        let room = Room::new(&id, game, self.store.clone());
+        room.save(&room.game.lock().unwrap());
+        list.insert(id.clone(), Arc::new(room));
+
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Room>> {
+        let list = self.list.lock().unwrap();
+        list.get(id).map(Arc::clone)
+    }
+
+    pub fn summaries(&self) -> Vec<RoomSummary> {
+        let list = self.list.lock().unwrap();
+        let mut summaries: Vec<RoomSummary> = list.values()
+            .map(|room| room.summary())
+            .collect();
+        summaries.sort_by(|a, b| a.id.cmp(&b.id));
+
+        summaries
+    }
+
+    /// Checks every room for a player who has run out of time
+    pub fn expire_turns(&self) {
+        let rooms: Vec<Arc<Room>> = self.list.lock().unwrap().values().map(Arc::clone).collect();
+        for room in rooms {
+            room.expire_turn();
+        }
+    }
+
+    /// Removes a room and tells anyone still connected to it that it is gone
+    pub fn remove(&self, id: &str) -> Option<Arc<Room>> {
+        let mut list = self.list.lock().unwrap();
+        let room = list.remove(id)?;
+        let _ = room.producer.send(BroadcastType::Closed);
+        if let Err(err) = self.store.remove(id) {
+            tracing::error!("failed to remove snapshot of room {}: {}", id, err);
+        }
+
+        Some(room)
+    }
+}
+
+#[cfg(test)]
+mod test;
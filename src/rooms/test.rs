@@ -0,0 +1,236 @@
+use super::*;
+use tokio::sync::broadcast::error::TryRecvError;
+use crate::games::hexagon::Event;
+use crate::games::hexagon::actions::{Actions, Target};
+use crate::store::MemoryStore;
+use std::time::UNIX_EPOCH;
+
+#[test]
+fn create_and_list_rooms() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    assert_eq!(rooms.summaries(), Vec::new());
+
+    let id1 = rooms.create();
+    let id2 = rooms.create();
+    assert_ne!(id1, id2);
+
+    let mut expected = vec![
+        RoomSummary { id: id1.clone(), phase: Phase::Boot, players: Vec::new() },
+        RoomSummary { id: id2.clone(), phase: Phase::Boot, players: Vec::new() }
+    ];
+    expected.sort_by(|a, b| a.id.cmp(&b.id));
+    assert_eq!(rooms.summaries(), expected);
+
+    assert!(rooms.get(&id1).is_some());
+    assert!(rooms.get("not_a_room").is_none());
+}
+
+#[test]
+fn remove_rooms() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let id = rooms.create();
+    let room = rooms.get(&id).unwrap();
+    let mut listener = room.producer.subscribe();
+
+    assert!(rooms.remove(&id).is_some());
+    assert!(rooms.get(&id).is_none());
+    assert!(rooms.remove(&id).is_none());
+    assert!(matches!(listener.try_recv(), Ok(BroadcastType::Closed)));
+}
+
+#[test]
+fn rooms_have_separate_games() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room1 = rooms.get(&rooms.create()).unwrap();
+    let room2 = rooms.get(&rooms.create()).unwrap();
+
+    let key1 = room1.add_player("name1").unwrap();
+    let key2 = room2.add_player("name2").unwrap();
+
+    assert!(room1.has_player(&key1));
+    assert!(!room1.has_player(&key2));
+    assert!(room2.has_player(&key2));
+    assert!(!room2.has_player(&key1));
+
+    // Restarting one room leaves the other alone
+    room1.start_game(Config::default()).unwrap();
+    assert!(!room1.has_player(&key1));
+    assert!(room2.has_player(&key2));
+}
+
+#[test]
+fn reject_impossible_games() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room = rooms.get(&rooms.create()).unwrap();
+    let key = room.add_player("name1").unwrap();
+
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 4}"
+    ).unwrap();
+    assert_eq!(room.start_game(config).unwrap_err().code, ErrorCode::InvalidConfig);
+
+    // The game that was already there is left alone
+    assert!(room.has_player(&key));
+}
+
+#[test]
+fn rooms_have_separate_channels() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room1 = rooms.get(&rooms.create()).unwrap();
+    let room2 = rooms.get(&rooms.create()).unwrap();
+
+    let mut listener1 = room1.producer.subscribe();
+    let mut listener2 = room2.producer.subscribe();
+
+    room2.producer.send(BroadcastType::Status).unwrap();
+    assert!(matches!(listener1.try_recv(), Err(TryRecvError::Empty)));
+    assert!(matches!(listener2.try_recv(), Ok(BroadcastType::Status)));
+}
+
+#[test]
+fn restore_rooms_from_snapshots() {
+    let store = Arc::new(MemoryStore::new());
+    let rooms = Rooms::new(store.clone());
+    let id1 = rooms.create();
+    let id2 = rooms.create();
+    let room1 = rooms.get(&id1).unwrap();
+    let key = room1.add_player("name1").unwrap();
+    room1.add_player("name2").unwrap();
+    rooms.remove(&id2);
+
+    let restored = Rooms::restore(store).unwrap();
+    assert!(restored.get(&id2).is_none());
+    let restored_room1 = restored.get(&id1).unwrap();
+    assert!(restored_room1.has_player(&key));
+    assert!(restored_room1.game.lock().unwrap().eq_ignoring_clock(&room1.game.lock().unwrap()));
+    assert_eq!(restored.summaries(), rooms.summaries());
+}
+
+#[test]
+fn restored_rooms_restart_the_turn() {
+    let store = Arc::new(MemoryStore::new());
+    let rooms = Rooms::new(store.clone());
+    let room = rooms.get(&rooms.create()).unwrap();
+    let config: Config = serde_json::from_str(
+        "{\"num_players\": 2, \"score_to_win\": 10, \"game_board_width\": 5, \"turn_time_limit\": 60}"
+    ).unwrap();
+    room.start_game(config).unwrap();
+    room.add_player("name1").unwrap();
+    room.add_player("name2").unwrap();
+    {
+        // As if the server had been down for a long time
+        let mut game = room.game.lock().unwrap();
+        game.start_turn_clock(UNIX_EPOCH);
+        room.save(&game);
+    }
+
+    let restored = Rooms::restore(store).unwrap();
+    restored.expire_turns();
+    let restored_room = restored.get(&room.id).unwrap();
+    assert_eq!(restored_room.game.lock().unwrap().get_log().events.len(), 2);
+}
+
+#[test]
+fn push_status_after_changes() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room = rooms.get(&rooms.create()).unwrap();
+    let mut listener = room.producer.subscribe();
+
+    room.add_player("name1").unwrap();
+    assert!(matches!(listener.try_recv(), Ok(BroadcastType::Status)));
+    room.add_player("name2").unwrap();
+    assert!(matches!(listener.try_recv(), Ok(BroadcastType::Status)));
+
+    // Nothing changed, so nothing gets sent
+    assert!(room.process_command("not_a_key", Command::new(Actions::EndTurn, String::from("not_a_key"))).is_err());
+    assert!(matches!(listener.try_recv(), Err(TryRecvError::Empty)));
+
+    room.start_game(Config::default()).unwrap();
+    assert!(matches!(listener.try_recv(), Ok(BroadcastType::Status)));
+    assert!(matches!(listener.try_recv(), Err(TryRecvError::Empty)));
+}
+
+#[test]
+fn commands_act_as_the_connection() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room = rooms.get(&rooms.create()).unwrap();
+    let key1 = room.add_player("name1").unwrap();
+    let key2 = room.add_player("name2").unwrap();
+    let (active, waiting) = {
+        let game = room.game.lock().unwrap();
+        if game.players.active_player.as_ref().unwrap().key == key1 { (key1, key2) } else { (key2, key1) }
+    };
+
+    let mut command = Command::new(Actions::PlaceVillageAndRoad, String::new());
+    command.target = [Some((Target::Node, 0)), Some((Target::Road, 0)), None, None, None];
+
+    // Naming the active player from someone else's connection gets turned down
+    let mut spoofed = command.clone();
+    spoofed.player = active.clone();
+    assert_eq!(room.process_command(&waiting, spoofed).unwrap_err().code, ErrorCode::Unauthorized);
+
+    // So does naming someone else from your own
+    let mut spoofed = command.clone();
+    spoofed.player = waiting.clone();
+    assert_eq!(room.process_command(&active, spoofed).unwrap_err().code, ErrorCode::Unauthorized);
+    assert!(room.game.lock().unwrap().get_log().events.iter().all(|e| matches!(e, Event::Joined {..})));
+
+    // Leaving the player out means the connection's own key
+    room.process_command(&active, command).unwrap();
+    let game = room.game.lock().unwrap();
+    assert!(matches!(
+        game.get_log().events.last(),
+        Some(Event::Acted { command, .. }) if command.player == active
+    ));
+}
+
+#[test]
+fn leave_untimed_turns_alone() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room = rooms.get(&rooms.create()).unwrap();
+    room.add_player("name1").unwrap();
+    room.add_player("name2").unwrap();
+    let mut listener = room.producer.subscribe();
+
+    rooms.expire_turns();
+    assert!(matches!(listener.try_recv(), Err(TryRecvError::Empty)));
+    assert_eq!(room.game.lock().unwrap().get_log().events.len(), 2);
+}
+
+#[test]
+fn share_history_without_keys() {
+    let rooms = Rooms::new(Arc::new(MemoryStore::new()));
+    let room = rooms.get(&rooms.create()).unwrap();
+    let key1 = room.add_player("name1").unwrap();
+    let key2 = room.add_player("name2").unwrap();
+    let active = room.game.lock().unwrap().players.active_player.as_ref().unwrap().key.clone();
+    let mut command = Command::new(Actions::PlaceVillageAndRoad, active.clone());
+    command.target = [Some((Target::Node, 0)), Some((Target::Road, 0)), None, None, None];
+    room.process_command(&active, command).unwrap();
+
+    // Only players can look, and what they get can't be used to sit in someone's seat
+    assert_eq!(room.history("not_a_key").unwrap_err().code, ErrorCode::Unauthorized);
+    let history = room.history(&key1).unwrap();
+    let shared = serde_json::to_string(&history).unwrap();
+    assert!(!shared.contains(&key1) && !shared.contains(&key2));
+    assert!(matches!(
+        history.log.events.last(),
+        Some(Event::Acted { command, .. }) if command.player.starts_with("seat")
+    ));
+
+    // Nor can it be used to see the rolls and cards still to come
+    let config = room.game.lock().unwrap().get_config().clone();
+    assert_ne!(config, config.clone().without_seed());
+    assert_eq!(history.config, config.without_seed());
+    assert_eq!(history.log.board_seed, None);
+
+    // A replay seats everyone again under brand new keys
+    let replay = rooms.get(&rooms.replay(history).unwrap()).unwrap();
+    let game = replay.game.lock().unwrap();
+    assert_eq!(game.players.list.len(), 2);
+    assert!(game.players.list.iter().all(|p| p.key != key1 && p.key != key2 && !p.key.starts_with("seat")));
+    assert!(matches!(
+        game.get_log().events.last(),
+        Some(Event::Acted { command, .. }) if game.players.list.iter().any(|p| p.key == command.player)
+    ));
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use serde_json::{from_str, to_string};
+
+use crate::games::hexagon::HexagonIsland;
+
+/// Somewhere to keep snapshots of every game so they survive a restart
+pub trait SnapshotStore: Send + Sync {
+    fn save(&self, room_id: &str, game: &HexagonIsland) -> io::Result<()>;
+    fn load_all(&self) -> io::Result<Vec<(String, HexagonIsland)>>;
+    fn remove(&self, room_id: &str) -> io::Result<()>;
+}
+
+/// Keeps one JSON file per room in a directory
+pub struct FileStore {
+    dir: PathBuf
+}
+
+impl FileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<FileStore> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(FileStore { dir })
+    }
+
+    fn path(&self, room_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", room_id))
+    }
+}
+
+impl SnapshotStore for FileStore {
+    fn save(&self, room_id: &str, game: &HexagonIsland) -> io::Result<()> {
+        // Write to a temporary file first so a crash can't leave half a snapshot behind
+        let temporary = self.dir.join(format!("{}.json.tmp", room_id));
+        fs::write(&temporary, to_string(game)?)?;
+        fs::rename(&temporary, self.path(room_id))
+    }
+
+    fn load_all(&self) -> io::Result<Vec<(String, HexagonIsland)>> {
+        let mut games = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") { continue; }
+            let room_id = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(stem) => String::from(stem),
+                None => continue
+            };
+            match from_str::<HexagonIsland>(&fs::read_to_string(&path)?) {
+                Ok(game) => games.push((room_id, game)),
+                Err(err) => tracing::warn!("skipping unreadable snapshot {:?}: {}", path, err)
+            }
+        }
+
+        Ok(games)
+    }
+
+    fn remove(&self, room_id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(room_id)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Keeps serialized snapshots in memory for the tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore {
+    snapshots: std::sync::Mutex<std::collections::HashMap<String, String>>
+}
+
+#[cfg(test)]
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore { snapshots: std::sync::Mutex::new(std::collections::HashMap::new()) }
+    }
+}
+
+#[cfg(test)]
+impl SnapshotStore for MemoryStore {
+    fn save(&self, room_id: &str, game: &HexagonIsland) -> io::Result<()> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        snapshots.insert(String::from(room_id), to_string(game)?);
+
+        Ok(())
+    }
+
+    fn load_all(&self) -> io::Result<Vec<(String, HexagonIsland)>> {
+        let snapshots = self.snapshots.lock().unwrap();
+        let mut games = Vec::new();
+        for (room_id, snapshot) in snapshots.iter() {
+            games.push((room_id.clone(), from_str::<HexagonIsland>(snapshot)?));
+        }
+
+        Ok(games)
+    }
+
+    fn remove(&self, room_id: &str) -> io::Result<()> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        snapshots.remove(room_id);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
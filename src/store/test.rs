@@ -0,0 +1,63 @@
+use super::*;
+use crate::games::core::traits::Game;
+
+fn game_in_progress() -> HexagonIsland {
+    let mut game = HexagonIsland::new();
+    game.add_player("key1", "name1").unwrap()
+        .add_player("key2", "name2").unwrap();
+    game
+}
+
+fn save_and_load(store: &dyn SnapshotStore) {
+    let game = game_in_progress();
+    store.save("room1", &game).unwrap();
+    store.save("room2", &HexagonIsland::new()).unwrap();
+
+    let mut games = store.load_all().unwrap();
+    games.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0], (String::from("room1"), game));
+    assert_eq!(games[1], (String::from("room2"), HexagonIsland::new()));
+
+    // Saving again replaces the old snapshot
+    let other_game = game_in_progress();
+    store.save("room2", &other_game).unwrap();
+    store.remove("room1").unwrap();
+    store.remove("not_a_room").unwrap();
+    let games = store.load_all().unwrap();
+    assert_eq!(games, vec![(String::from("room2"), other_game)]);
+}
+
+#[test]
+fn memory_store() {
+    save_and_load(&MemoryStore::new());
+}
+
+#[test]
+fn file_store() {
+    let dir = std::env::temp_dir().join(format!("game-serve-rs-store-{}", std::process::id()));
+    let store = FileStore::new(&dir).unwrap();
+    save_and_load(&store);
+
+    // Files that aren't snapshots are ignored
+    fs::write(dir.join("notes.txt"), "not a game").unwrap();
+    fs::write(dir.join("broken.json"), "{").unwrap();
+    assert_eq!(store.load_all().unwrap().len(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn load_older_snapshots() {
+    let store = MemoryStore::new();
+    let game = game_in_progress();
+    // The RNG state doesn't fit in a `Value`, but it's about to go anyway
+    let mut snapshot: serde_json::Value = serde_json::from_str(&to_string(&game).unwrap()).unwrap();
+    snapshot.as_object_mut().unwrap().remove("rng");
+    store.snapshots.lock().unwrap().insert(String::from("room1"), snapshot.to_string());
+
+    let games = store.load_all().unwrap();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].1.get_phase(), game.get_phase());
+    assert_eq!(games[0].1.get_layout(), game.get_layout());
+}